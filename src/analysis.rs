@@ -0,0 +1,146 @@
+use super::{legal_moves, BitBoard, Bits, GameEvent, Map, Player, Polynomio, Shape};
+use specs::prelude::*;
+
+/// Weights of the evaluation: a placed cell is worth 1.
+const TERRITORY_WEIGHT: f32 = 0.5;
+const CORNER_WEIGHT: f32 = 1.0;
+
+/// What a colour can still do from the current position.
+#[derive(Clone, Debug)]
+pub struct Analysis {
+    pub player_id: i32,
+    /// Legal placements of the remaining pieces, one per distinct orientation and cell.
+    pub moves: usize,
+    /// Empty cells where a piece can start.
+    pub corners: usize,
+    /// Empty cells that the next move can cover.
+    pub territory: usize,
+    /// Indices of the pieces that have a legal placement.
+    pub pieces: Vec<usize>,
+    /// Placed cells, territory and corners weighed together, minus the average of
+    /// the other colours. Positive means ahead.
+    pub eval: f32,
+}
+
+/// The last analysis and the history it was computed for.
+#[derive(Default)]
+pub struct AnalysisCache {
+    history: Option<Vec<GameEvent>>,
+    analyses: Vec<Analysis>,
+}
+
+/// Analysis of every colour, by player id. Computed again only after a move.
+pub fn analyze(ecs: &World) -> Vec<Analysis> {
+    let history = ecs.fetch::<Vec<GameEvent>>();
+    let mut cache = ecs.fetch_mut::<AnalysisCache>();
+    if cache.history.as_ref() != Some(&*history) {
+        cache.analyses = evaluate(ecs);
+        cache.history = Some(history.to_vec());
+    }
+    cache.analyses.clone()
+}
+
+/// Cells the player has placed, and the pieces they can still play.
+fn pieces_of<'a>(
+    player: &Player,
+    polynomios: &'a ReadStorage<Polynomio>,
+) -> (usize, Vec<(usize, &'a Shape)>) {
+    let mut placed = 0;
+    let mut shapes = Vec::new();
+    for (i, (e, fixed)) in player.polynomios.iter().zip(player.fixed.iter()).enumerate() {
+        let polynomio = polynomios.get(*e).unwrap();
+        if *fixed {
+            placed += polynomio.coods.len();
+        } else if !player.end {
+            shapes.push((i, &polynomio.shape));
+        }
+    }
+    (placed, shapes)
+}
+
+fn evaluate(ecs: &World) -> Vec<Analysis> {
+    let map = ecs.fetch::<Map>();
+    let board = BitBoard::from_map(&map);
+    let players = ecs.read_storage::<Player>();
+    let polynomios = ecs.read_storage::<Polynomio>();
+
+    let mut analyses: Vec<(Analysis, f32)> = ecs
+        .fetch::<Vec<Entity>>()
+        .iter()
+        .filter_map(|e| players.get(*e))
+        .map(|player| {
+            let (placed, shapes) = pieces_of(player, &polynomios);
+
+            let moves = legal_moves(&board, player.id, &shapes);
+            let mut territory = Bits::new(map.map.len());
+            for m in moves.iter() {
+                for cood in m.coods.iter() {
+                    territory.set(map.point_idx(*cood + m.position));
+                }
+            }
+            let mut pieces: Vec<usize> = moves.iter().map(|m| m.piece).collect();
+            pieces.dedup();
+            let corners = if player.end {
+                0
+            } else {
+                board.anchor_bits(player.id).count()
+            };
+
+            let value = placed as f32
+                + TERRITORY_WEIGHT * territory.count() as f32
+                + CORNER_WEIGHT * corners as f32;
+            let analysis = Analysis {
+                player_id: player.id,
+                moves: moves.len(),
+                corners,
+                territory: territory.count(),
+                pieces,
+                eval: 0.,
+            };
+            (analysis, value)
+        })
+        .collect();
+
+    let total: f32 = analyses.iter().map(|(_, value)| value).sum();
+    let n = analyses.len();
+    for (analysis, value) in analyses.iter_mut() {
+        analysis.eval = if n > 1 {
+            *value - (total - *value) / (n - 1) as f32
+        } else {
+            *value
+        };
+    }
+    analyses.into_iter().map(|(analysis, _)| analysis).collect()
+}
+
+/// The last heatmap and the history and depth it was computed for.
+#[derive(Default)]
+pub struct HeatmapCache {
+    key: Option<(Vec<GameEvent>, usize)>,
+    reach: Vec<(i32, Bits)>,
+}
+
+/// Cells each colour can cover within `depth` moves, by player id, as in `BitBoard::reach`.
+pub fn heatmap(ecs: &World, depth: usize) -> Vec<(i32, Bits)> {
+    let history = ecs.fetch::<Vec<GameEvent>>();
+    let mut cache = ecs.fetch_mut::<HeatmapCache>();
+    let key = Some((history.to_vec(), depth));
+    if cache.key != key {
+        let map = ecs.fetch::<Map>();
+        let board = BitBoard::from_map(&map);
+        let players = ecs.read_storage::<Player>();
+        let polynomios = ecs.read_storage::<Polynomio>();
+        cache.reach = ecs
+            .fetch::<Vec<Entity>>()
+            .iter()
+            .filter_map(|e| players.get(*e))
+            .map(|player| {
+                let (_, shapes) = pieces_of(player, &polynomios);
+                let shapes: Vec<&Shape> = shapes.into_iter().map(|(_, shape)| shape).collect();
+                (player.id, board.reach(player.id, &shapes, depth))
+            })
+            .collect();
+        cache.key = key;
+    }
+    cache.reach.clone()
+}
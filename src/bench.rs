@@ -0,0 +1,88 @@
+use super::{legal_moves, BitBoard, GameModeDef, Move, Shape};
+use rand::prelude::*;
+use rltk::Point;
+use std::time::{Duration, Instant};
+
+/// Plays random games of the mode and times the move generation on `Map` and on
+/// `BitBoard` in every position, checking that both find the same moves.
+pub fn bench(game_mode: &GameModeDef, games: usize, seed: u64) {
+    let piece_set = game_mode.piece_set().unwrap();
+    let shapes: Vec<Shape> = (0..piece_set.pieces.len())
+        .map(|id| piece_set.shape(id))
+        .collect();
+    let n_players = game_mode.players.len();
+    let turn_order = game_mode.seating().turn_order;
+
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut map_time = Duration::ZERO;
+    let mut bitboard_time = Duration::ZERO;
+    let mut positions = 0;
+    let mut moves = 0;
+    for _ in 0..games {
+        let mut map = game_mode.build_map();
+        for (id, player) in game_mode.players.iter().enumerate() {
+            map.starts
+                .insert(id as i32, Point::new(player.start.0, player.start.1));
+        }
+        let mut board = BitBoard::from_map(&map);
+        let mut remaining: Vec<Vec<usize>> = vec![(0..shapes.len()).collect(); n_players];
+        let mut passed = vec![false; n_players];
+
+        while passed.iter().any(|passed| !passed) {
+            for &id in turn_order.iter() {
+                if passed[id] {
+                    continue;
+                }
+                let pieces: Vec<(usize, &Shape)> =
+                    remaining[id].iter().map(|&i| (i, &shapes[i])).collect();
+
+                let started = Instant::now();
+                let map_moves = legal_moves(&map, id as i32, &pieces);
+                map_time += started.elapsed();
+                let started = Instant::now();
+                let bitboard_moves = legal_moves(&board, id as i32, &pieces);
+                bitboard_time += started.elapsed();
+
+                if key(&map_moves) != key(&bitboard_moves) {
+                    eprintln!("[ERROR] Map and BitBoard disagree on the legal moves");
+                    std::process::exit(1);
+                }
+                positions += 1;
+                moves += bitboard_moves.len();
+
+                match bitboard_moves.choose(&mut rng) {
+                    Some(m) => {
+                        map.put(m.position, &m.coods, id as i32);
+                        board.put(m.position, &m.coods, id as i32);
+                        remaining[id].retain(|&i| i != m.piece);
+                    }
+                    None => passed[id] = true,
+                }
+            }
+        }
+    }
+
+    println!(
+        "{} games, {} positions, {} legal moves",
+        games, positions, moves
+    );
+    let per_second = |time: Duration| moves as f64 / time.as_secs_f64().max(1e-9);
+    println!(
+        "Map:      {:>10.0} moves/s ({:.3}s)",
+        per_second(map_time),
+        map_time.as_secs_f64()
+    );
+    println!(
+        "BitBoard: {:>10.0} moves/s ({:.3}s), {:.1}x",
+        per_second(bitboard_time),
+        bitboard_time.as_secs_f64(),
+        map_time.as_secs_f64() / bitboard_time.as_secs_f64().max(1e-9)
+    );
+}
+
+fn key(moves: &[Move]) -> Vec<(usize, usize, i32, i32)> {
+    moves
+        .iter()
+        .map(|m| (m.piece, m.orientation, m.position.x, m.position.y))
+        .collect()
+}
@@ -0,0 +1,323 @@
+use super::{Board, Geometry, Map, Shape, EMPTY};
+use rltk::Point;
+
+/// A set of board cells, one bit per cell index of the `Map`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Bits {
+    words: Vec<u64>,
+}
+
+impl Bits {
+    pub fn new(len: usize) -> Self {
+        Bits {
+            words: vec![0; (len + 63) / 64],
+        }
+    }
+
+    pub fn get(&self, idx: usize) -> bool {
+        self.words[idx / 64] & (1 << (idx % 64)) != 0
+    }
+
+    pub fn set(&mut self, idx: usize) {
+        self.words[idx / 64] |= 1 << (idx % 64);
+    }
+
+    pub fn clear(&mut self, idx: usize) {
+        self.words[idx / 64] &= !(1 << (idx % 64));
+    }
+
+    pub fn count(&self) -> usize {
+        self.words.iter().map(|w| w.count_ones() as usize).sum()
+    }
+
+    /// Indices of the set bits, in increasing order.
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        self.words.iter().enumerate().flat_map(|(i, &word)| {
+            let mut word = word;
+            std::iter::from_fn(move || {
+                if word == 0 {
+                    return None;
+                }
+                let bit = word.trailing_zeros() as usize;
+                word &= word - 1;
+                Some(i * 64 + bit)
+            })
+        })
+    }
+}
+
+/// Cells of one player. A cell is forbidden while one of the player's pieces
+/// shares a side with it, and a corner while one touches it by a corner.
+/// The counts let a removal undo a placement exactly.
+#[derive(Clone, Debug)]
+struct PlayerBits {
+    occupied: Bits,
+    forbidden: Bits,
+    corners: Bits,
+    forbidden_count: Vec<u8>,
+    corner_count: Vec<u8>,
+    start: Option<usize>,
+}
+
+/// The board as bit sets, for bots and analysis. Built from a `Map` and kept
+/// up to date by `put` and `remove` without scanning the neighbours of every cell.
+#[derive(Clone, Debug)]
+pub struct BitBoard {
+    width: usize,
+    height: usize,
+    geometry: Geometry,
+    free_placement: bool,
+    empty: Bits,
+    players: Vec<PlayerBits>,
+}
+
+impl BitBoard {
+    pub fn from_map(map: &Map) -> Self {
+        let len = map.map.len();
+        let n_players = map
+            .starts
+            .keys()
+            .chain(map.colors.keys())
+            .chain(map.map.iter().filter(|&&tile| tile >= 0))
+            .map(|&id| id as usize + 1)
+            .max()
+            .unwrap_or(0);
+
+        let mut board = BitBoard {
+            width: map.width,
+            height: map.height,
+            geometry: map.geometry,
+            free_placement: map.free_placement,
+            empty: Bits::new(len),
+            players: (0..n_players)
+                .map(|id| PlayerBits {
+                    occupied: Bits::new(len),
+                    forbidden: Bits::new(len),
+                    corners: Bits::new(len),
+                    forbidden_count: vec![0; len],
+                    corner_count: vec![0; len],
+                    start: map.starts.get(&(id as i32)).map(|&p| map.point_idx(p)),
+                })
+                .collect(),
+        };
+        for (idx, &tile) in map.map.iter().enumerate() {
+            if tile == EMPTY {
+                board.empty.set(idx);
+            } else if tile >= 0 {
+                board.occupy(idx, tile as usize);
+            }
+        }
+        board
+    }
+
+    pub fn empty_cells(&self) -> usize {
+        self.empty.count()
+    }
+
+    pub fn first_empty(&self) -> Option<Point> {
+        self.empty.iter().next().map(|idx| self.idx_point(idx))
+    }
+
+    /// Places the cells without checking the rules.
+    pub fn put(&mut self, position: Point, coods: &[Point], player_id: i32) {
+        for cood in coods {
+            let idx = self.point_idx(*cood + position);
+            self.empty.clear(idx);
+            self.occupy(idx, player_id as usize);
+        }
+    }
+
+    pub fn remove(&mut self, position: Point, coods: &[Point], player_id: i32) {
+        for cood in coods {
+            let idx = self.point_idx(*cood + position);
+            self.empty.set(idx);
+            self.vacate(idx, player_id as usize);
+        }
+    }
+
+    /// Empty cells where the next piece of the player can start, as in `Map::anchors`.
+    pub fn anchor_bits(&self, player_id: i32) -> Bits {
+        if self.free_placement {
+            return self.empty.clone();
+        }
+        // A player without a start or pieces has nowhere to play.
+        let player = match self.players.get(player_id as usize) {
+            Some(player) => player,
+            None => return Bits::new(self.width * self.height),
+        };
+        let mut anchors = Bits {
+            words: self
+                .empty
+                .words
+                .iter()
+                .zip(player.corners.words.iter())
+                .zip(player.forbidden.words.iter())
+                .map(|((empty, corners), forbidden)| empty & corners & !forbidden)
+                .collect(),
+        };
+        if let Some(start) = player.start.filter(|&start| self.empty.get(start)) {
+            anchors.set(start);
+        }
+        anchors
+    }
+
+    /// Cells the player can cover within `depth` moves of the given shapes. Beyond
+    /// the next move this is an upper bound: later moves may start from any corner
+    /// of the cells reached so far, pieces are not used up and the moves may touch
+    /// each other by a side.
+    pub fn reach(&self, player_id: i32, shapes: &[&Shape], depth: usize) -> Bits {
+        let len = self.width * self.height;
+        let mut reached = Bits::new(len);
+        let mut anchors = self.anchor_bits(player_id);
+        for _ in 0..depth {
+            let mut new = Bits::new(len);
+            for anchor in anchors.iter() {
+                let anchor = self.idx_point(anchor);
+                for shape in shapes.iter() {
+                    for orientation in shape.distinct() {
+                        let coods = shape.cells(orientation);
+                        for cood in coods.iter() {
+                            let position = anchor - *cood;
+                            // A triangle moved by an odd offset would be turned over.
+                            if self.geometry == Geometry::Triangle
+                                && (position.x + position.y).rem_euclid(2) != 0
+                            {
+                                continue;
+                            }
+                            if self.fits(position, coods, player_id) {
+                                for cood in coods.iter() {
+                                    new.set(self.point_idx(*cood + position));
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+
+            let forbidden = match self.players.get(player_id as usize) {
+                Some(player) => &player.forbidden,
+                None => break,
+            };
+            anchors = Bits::new(len);
+            for idx in new.iter().filter(|&idx| !reached.get(idx)) {
+                let p = self.idx_point(idx);
+                for n in self.neighbors(p, self.geometry.edge_neighbors(p)) {
+                    if self.empty.get(n) && (self.free_placement || !forbidden.get(n)) {
+                        anchors.set(n);
+                    }
+                }
+            }
+            for idx in new.iter() {
+                reached.set(idx);
+            }
+        }
+        reached
+    }
+
+    /// Whether the cells are empty and do not share a side with the player's pieces.
+    fn fits(&self, position: Point, coods: &[Point], player_id: i32) -> bool {
+        let player = match self.players.get(player_id as usize) {
+            Some(player) => player,
+            None => return false,
+        };
+        coods.iter().all(|cood| {
+            let p = *cood + position;
+            if p.x < 0 || p.x >= self.width as i32 || p.y < 0 || p.y >= self.height as i32 {
+                return false;
+            }
+            let idx = self.point_idx(p);
+            self.empty.get(idx) && (self.free_placement || !player.forbidden.get(idx))
+        })
+    }
+
+    fn occupy(&mut self, idx: usize, player_id: usize) {
+        let p = self.idx_point(idx);
+        let geometry = self.geometry;
+        let line: Vec<usize> = self.neighbors(p, geometry.line_neighbors(p)).collect();
+        let edge: Vec<usize> = self.neighbors(p, geometry.edge_neighbors(p)).collect();
+
+        let player = &mut self.players[player_id];
+        player.occupied.set(idx);
+        for n in line {
+            player.forbidden_count[n] += 1;
+            player.forbidden.set(n);
+        }
+        for n in edge {
+            player.corner_count[n] += 1;
+            player.corners.set(n);
+        }
+    }
+
+    fn vacate(&mut self, idx: usize, player_id: usize) {
+        let p = self.idx_point(idx);
+        let geometry = self.geometry;
+        let line: Vec<usize> = self.neighbors(p, geometry.line_neighbors(p)).collect();
+        let edge: Vec<usize> = self.neighbors(p, geometry.edge_neighbors(p)).collect();
+
+        let player = &mut self.players[player_id];
+        player.occupied.clear(idx);
+        for n in line {
+            player.forbidden_count[n] -= 1;
+            if player.forbidden_count[n] == 0 {
+                player.forbidden.clear(n);
+            }
+        }
+        for n in edge {
+            player.corner_count[n] -= 1;
+            if player.corner_count[n] == 0 {
+                player.corners.clear(n);
+            }
+        }
+    }
+
+    fn neighbors<'a>(
+        &self,
+        p: Point,
+        offsets: &'a [(i32, i32)],
+    ) -> impl Iterator<Item = usize> + 'a {
+        let (width, height) = (self.width as i32, self.height as i32);
+        offsets
+            .iter()
+            .map(move |&(dx, dy)| (p.x + dx, p.y + dy))
+            .filter(move |&(x, y)| x >= 0 && x < width && y >= 0 && y < height)
+            .map(move |(x, y)| (x + y * width) as usize)
+    }
+
+    fn point_idx(&self, p: Point) -> usize {
+        (p.x + p.y * self.width as i32) as usize
+    }
+
+    fn idx_point(&self, idx: usize) -> Point {
+        Point::new((idx % self.width) as i32, (idx / self.width) as i32)
+    }
+}
+
+impl Board for BitBoard {
+    fn geometry(&self) -> Geometry {
+        self.geometry
+    }
+
+    fn can_put(&self, position: Point, coods: &[Point], player_id: i32) -> bool {
+        let player = &self.players[player_id as usize];
+        let mut touch = false;
+        for cood in coods {
+            let p = *cood + position;
+            if p.x < 0 || p.x >= self.width as i32 || p.y < 0 || p.y >= self.height as i32 {
+                return false;
+            }
+            let idx = self.point_idx(p);
+            if !self.empty.get(idx) || (!self.free_placement && player.forbidden.get(idx)) {
+                return false;
+            }
+            touch |= player.corners.get(idx) || player.start == Some(idx);
+        }
+        self.free_placement || touch
+    }
+
+    fn anchors(&self, player_id: i32) -> Vec<Point> {
+        self.anchor_bits(player_id)
+            .iter()
+            .map(|idx| self.idx_point(idx))
+            .collect()
+    }
+}
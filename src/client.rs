@@ -84,7 +84,7 @@ impl Client {
                 }
                 formatted
             };
-            println!("Got a message: {}", truncated);
+            log!("Got a message: {}", truncated);
             Some(message)
         }
     }
@@ -1,6 +1,6 @@
-use super::Map;
+use super::{Geometry, Map, PieceSet, Shape};
 use crate::entity_vec::EntityVec;
-use crate::Mode;
+use crate::{GameEvent, Mode, Rules, Seating, Series, UndoRequest};
 use rltk::{Point, RGB};
 use serde::{Deserialize, Serialize};
 use specs::error::NoError;
@@ -36,6 +36,7 @@ impl Position {
     }
 
     pub fn translate_within(&mut self, delta_x: i32, delta_y: i32, map: &Map) {
+        let (delta_x, delta_y) = map.geometry.step(delta_x, delta_y, self.to_point());
         let m = 2;
         let nx = self.x + delta_x;
         let ny = self.y + delta_y;
@@ -60,45 +61,48 @@ impl Position {
 
 #[derive(Component, ConvertSaveload, Clone)]
 pub struct Polynomio {
+    pub name: String,
+    pub shape: Shape,
+    pub orientation: usize,
+    /// Cells of the shape in the current orientation.
     pub coods: Vec<Point>,
-    pub orig_coods: Vec<Point>,
     pub color: RGB,
     pub fixed: bool,
     pub bg: bool,
 }
 
 impl Polynomio {
-    pub fn new(coods: Vec<Point>, color: RGB, bg: bool) -> Self {
+    pub fn new(name: String, shape: Shape, color: RGB, bg: bool) -> Self {
         Polynomio {
-            coods: coods.clone(),
-            orig_coods: coods,
+            name,
+            coods: shape.cells(0).to_vec(),
+            shape,
+            orientation: 0,
             color,
             fixed: false,
             bg,
         }
     }
 
+    pub fn geometry(&self) -> Geometry {
+        self.shape.geometry
+    }
+
     pub fn rotate(&mut self, right: bool) {
-        let s = if right { -1 } else { 1 };
-        self.transform((0, s), (-s, 0));
+        self.orient(self.shape.rotate(self.orientation, right));
     }
 
     pub fn flip(&mut self) {
-        self.transform((-1, 0), (0, 1));
+        self.orient(self.shape.flip(self.orientation));
     }
 
     pub fn reset(&mut self) {
-        self.coods = self.orig_coods.clone();
+        self.orient(0);
     }
 
-    fn transform(&mut self, row1: (i32, i32), row2: (i32, i32)) {
-        for cood in &mut self.coods {
-            let tx = cood.x;
-            let ty = cood.y;
-
-            cood.x = row1.0 * tx + row1.1 * ty;
-            cood.y = row2.0 * tx + row2.1 * ty;
-        }
+    pub fn orient(&mut self, orientation: usize) {
+        self.orientation = orientation;
+        self.coods = self.shape.cells(orientation).to_vec();
     }
 
     pub fn upper_left(&self) -> Point {
@@ -119,10 +123,14 @@ pub struct Player {
     pub fixed: Vec<bool>,
     pub color: RGB,
     pub end: bool,
+    /// 0 for a colour that is not ranked (a shared colour).
     pub rank: i32,
+    pub tied: bool,
     pub remaining_tiles: i32,
+    pub score: i32,
     pub name: Option<String>,
     pub cursor: Point,
+    pub time_used: f32,
 }
 
 impl Player {
@@ -136,11 +144,25 @@ impl Player {
             color: color,
             end: false,
             rank: 0,
+            tied: false,
             remaining_tiles: 0,
+            score: 0,
             name: name,
             cursor: Point::new(0, 0),
+            time_used: 0.,
         };
     }
+
+    pub fn display_name(&self) -> String {
+        match &self.name {
+            Some(name) => name.clone(),
+            None => format!("Player #{}", self.id + 1),
+        }
+    }
+
+    pub fn placed(&self) -> usize {
+        self.fixed.iter().filter(|&&fixed| fixed).count()
+    }
 }
 
 #[derive(Component)]
@@ -154,5 +176,11 @@ pub struct SerializeHelper {
     pub map: Map,
     pub active_player_id: usize,
     pub mode: Mode,
+    pub rules: Rules,
+    pub undo_request: Option<UndoRequest>,
+    pub history: Vec<GameEvent>,
+    pub series: Option<Series>,
+    pub seating: Seating,
+    pub piece_set: PieceSet,
     // pub players: EntityVec<Entity>,
 }
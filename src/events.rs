@@ -1,70 +1,84 @@
-use super::{Map, Player, Polynomio, Position, State};
+use super::{Map, Player, Polynomio, Position};
 use rltk::Point;
+use serde::{Deserialize, Serialize};
 use specs::prelude::*;
 use specs::Entity;
 
-pub trait Event {
-    fn undo(&mut self, gs: &mut State);
-    fn should_chain_next(&self) -> bool;
+/// An entry of the game history. Events refer to players and pieces by their
+/// ids instead of `Entity` handles so that they stay valid across sync and reload.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub enum GameEvent {
+    Put {
+        player_id: i32,
+        polynomio_id: usize,
+        x: i32,
+        y: i32,
+        /// Orientation index of the piece, see `Shape`.
+        #[serde(default)]
+        orientation: usize,
+    },
+    TurnChange {
+        from: usize,
+    },
+    GiveUp {
+        player_id: i32,
+    },
 }
 
-pub struct PutEvent {
-    pub player_entity: Entity,
-    pub polynomio_id: usize,
-}
-
-impl Event for PutEvent {
-    fn undo(&mut self, gs: &mut State) {
-        let mut map = gs.ecs.fetch_mut::<Map>();
-        let mut player_store = gs.ecs.write_storage::<Player>();
-        let player = player_store.get_mut(self.player_entity).unwrap();
-        let polynomio_entity = player.polynomios[self.polynomio_id];
-        let mut position_store = gs.ecs.write_storage::<Position>();
-        let mut polynomio_store = gs.ecs.write_storage::<Polynomio>();
-        let polynomio = polynomio_store.get_mut(polynomio_entity).unwrap();
-        let position = position_store.get_mut(polynomio_entity).unwrap();
+impl GameEvent {
+    pub fn undo(&self, ecs: &World) {
+        match self {
+            GameEvent::Put {
+                player_id,
+                polynomio_id,
+                ..
+            } => {
+                let player_entity = ecs.fetch::<Vec<Entity>>()[*player_id as usize];
+                let mut map = ecs.fetch_mut::<Map>();
+                let mut player_store = ecs.write_storage::<Player>();
+                let player = player_store.get_mut(player_entity).unwrap();
+                let polynomio_entity = player.polynomios[*polynomio_id];
+                let mut position_store = ecs.write_storage::<Position>();
+                let mut polynomio_store = ecs.write_storage::<Polynomio>();
+                let polynomio = polynomio_store.get_mut(polynomio_entity).unwrap();
+                let position = position_store.get_mut(polynomio_entity).unwrap();
 
-        let remove_pos = Point::new(position.x - map.x, position.y - map.y);
-        if !map.try_remove(remove_pos, polynomio, player.id) {
-            panic!("failed to undo");
+                let remove_pos = Point::new(position.x - map.x, position.y - map.y);
+                if !map.try_remove(remove_pos, polynomio, player.id) {
+                    panic!("failed to undo");
+                }
+                player.fixed[*polynomio_id] = false;
+                player.select = *polynomio_id;
+                position.reset();
+                polynomio.reset();
+            }
+            GameEvent::TurnChange { from } => {
+                let mut active_player_id = ecs.fetch_mut::<usize>();
+                *active_player_id = *from;
+            }
+            GameEvent::GiveUp { player_id } => {
+                let player_entity = ecs.fetch::<Vec<Entity>>()[*player_id as usize];
+                let mut player_store = ecs.write_storage::<Player>();
+                let player = player_store.get_mut(player_entity).unwrap();
+                player.end = false;
+            }
         }
-        player.fixed[self.polynomio_id] = false;
-        player.select = self.polynomio_id;
-        position.reset();
-        polynomio.reset();
-    }
-    fn should_chain_next(&self) -> bool {
-        false
-    }
-}
-
-pub struct TurnChangeEvent {
-    pub from: usize,
-}
-
-impl Event for TurnChangeEvent {
-    fn undo(&mut self, gs: &mut State) {
-        let mut active_player_id = gs.ecs.fetch_mut::<usize>();
-        *active_player_id = self.from;
     }
 
-    fn should_chain_next(&self) -> bool {
-        true
+    pub fn should_chain_next(&self) -> bool {
+        matches!(self, GameEvent::TurnChange { .. })
     }
-}
 
-pub struct GiveUpEvent {
-    pub player_entity: Entity,
-}
-
-impl Event for GiveUpEvent {
-    fn undo(&mut self, gs: &mut State) {
-        let mut player_store = gs.ecs.write_storage::<Player>();
-        let mut player = player_store.get_mut(self.player_entity).unwrap();
-        player.end = false;
+    pub fn player_id(&self) -> i32 {
+        match self {
+            GameEvent::Put { player_id, .. } => *player_id,
+            GameEvent::TurnChange { from } => *from as i32,
+            GameEvent::GiveUp { player_id } => *player_id,
+        }
     }
 
-    fn should_chain_next(&self) -> bool {
-        false
+    /// Whether the event shows up in the move list.
+    pub fn is_move(&self) -> bool {
+        !matches!(self, GameEvent::TurnChange { .. })
     }
 }
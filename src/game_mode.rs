@@ -0,0 +1,203 @@
+use super::{Geometry, Map, Owner, PieceSet, PieceSetRef, Seating, EMPTY, WALL};
+use rltk::RGB;
+use serde::{Deserialize, Serialize};
+use std::io::{Error, ErrorKind};
+
+/// Modes shipped with the game. `-m <name>` picks one of them.
+const BUILTIN_MODES: [(&str, &str); 8] = [
+    ("normal", include_str!("../modes/normal.json")),
+    ("duo", include_str!("../modes/duo.json")),
+    ("trio", include_str!("../modes/trio.json")),
+    ("classic", include_str!("../modes/classic.json")),
+    ("trigon", include_str!("../modes/trigon.json")),
+    ("puzzle", include_str!("../modes/puzzle.json")),
+    ("pentomino", include_str!("../modes/pentomino.json")),
+    ("debug", include_str!("../modes/debug.json")),
+];
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "shape", rename_all = "lowercase")]
+pub enum BoardShape {
+    /// A rectangle of squares. The outermost cells are walls.
+    Rectangle { width: usize, height: usize },
+    /// A hexagon of triangles with `side` triangles on each edge.
+    Hexagon { side: usize },
+    /// Any shape, one string per row: '.' is a cell, '#' an obstacle and ' ' a hole.
+    Mask {
+        rows: Vec<String>,
+        #[serde(default)]
+        geometry: Geometry,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct BoardDef {
+    pub x: i32,
+    pub y: i32,
+    #[serde(flatten)]
+    pub shape: BoardShape,
+    /// Cells that can never be covered.
+    #[serde(default)]
+    pub blocked: Vec<(i32, i32)>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PlayerDef {
+    /// Where the pieces of the player are laid out before they are put.
+    pub tray: (i32, i32),
+    pub color: (f32, f32, f32),
+    pub start: (i32, i32),
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Seat controlling this colour. Defaults to one seat per colour.
+    #[serde(default)]
+    pub seat: Option<usize>,
+    /// Played in turn by every seat and not scored.
+    #[serde(default)]
+    pub shared: bool,
+}
+
+/// A game mode: the board, the players and the pieces they play with.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct GameModeDef {
+    #[serde(default)]
+    pub description: String,
+    pub board: BoardDef,
+    /// The piece set every player gets.
+    pub pieces: PieceSetRef,
+    pub players: Vec<PlayerDef>,
+    /// Order in which the colours play. Defaults to the order of `players`.
+    #[serde(default)]
+    pub turn_order: Option<Vec<usize>>,
+    /// A solo game where the goal is to place every piece.
+    #[serde(default)]
+    pub puzzle: bool,
+    /// Pieces may go anywhere they fit, without the corner rule.
+    #[serde(default)]
+    pub free_placement: bool,
+}
+
+impl GameModeDef {
+    pub fn builtin(name: &str) -> Option<Self> {
+        BUILTIN_MODES
+            .iter()
+            .find(|(builtin, _)| *builtin == name)
+            .map(|(_, json)| Self::parse(json).expect("Broken built-in game mode."))
+    }
+
+    pub fn load(path: &str) -> std::io::Result<Self> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    fn parse(json: &str) -> std::io::Result<Self> {
+        let mode: GameModeDef =
+            serde_json::from_str(json).map_err(|err| Error::new(ErrorKind::InvalidData, err))?;
+        mode.validate()
+            .map_err(|err| Error::new(ErrorKind::InvalidData, err))?;
+        Ok(mode)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.players.is_empty() {
+            return Err("A game mode needs at least one player.".to_string());
+        }
+        match &self.board.shape {
+            // The outermost cells are walls.
+            BoardShape::Rectangle { width, height } if *width < 3 || *height < 3 => {
+                return Err(
+                    "A rectangle board needs a width and a height of at least 3.".to_string(),
+                );
+            }
+            BoardShape::Hexagon { side: 0 } => {
+                return Err("A hexagon board needs a side of at least 1.".to_string());
+            }
+            BoardShape::Mask { rows, .. } if !rows.iter().any(|row| row.contains('.')) => {
+                return Err("The board mask has no cell.".to_string());
+            }
+            _ => {}
+        }
+        let map = self.build_map();
+        if self.piece_set()?.geometry != map.geometry {
+            return Err("The pieces do not fit the cells of the board.".to_string());
+        }
+        for (i, player) in self.players.iter().enumerate() {
+            let (x, y) = player.start;
+            if !map.xy_isin(x, y) || map.map[map.xy_idx(x, y)] != EMPTY {
+                return Err(format!("The start of player #{} is not on the board.", i + 1));
+            }
+        }
+        if self.puzzle && self.players.len() != 1 {
+            return Err("A puzzle is played by a single player.".to_string());
+        }
+        if let Some(order) = &self.turn_order {
+            let mut sorted = order.clone();
+            sorted.sort_unstable();
+            if sorted != (0..self.players.len()).collect::<Vec<usize>>() {
+                return Err("The turn order must list every player once.".to_string());
+            }
+        }
+        Ok(())
+    }
+
+    pub fn piece_set(&self) -> Result<PieceSet, String> {
+        self.pieces.resolve()
+    }
+
+    pub fn color(&self, id: usize) -> RGB {
+        let (r, g, b) = self.players[id].color;
+        RGB::from_f32(r, g, b)
+    }
+
+    /// Number of seats, which is the number of people who can join the game.
+    pub fn seats(&self) -> usize {
+        self.seating().seats
+    }
+
+    pub fn seating(&self) -> Seating {
+        let owners: Vec<Owner> = self
+            .players
+            .iter()
+            .enumerate()
+            .map(|(i, player)| match (player.shared, player.seat) {
+                (true, _) => Owner::Shared,
+                (false, Some(seat)) => Owner::Seat(seat),
+                (false, None) => Owner::Seat(i),
+            })
+            .collect();
+        let seats = owners
+            .iter()
+            .filter_map(|owner| match owner {
+                Owner::Seat(seat) => Some(seat + 1),
+                Owner::Shared => None,
+            })
+            .max()
+            .unwrap_or(0);
+
+        let seating = Seating::new(seats, owners);
+        match &self.turn_order {
+            Some(order) => seating.with_turn_order(order.clone()),
+            None => seating,
+        }
+    }
+
+    /// The board without players bound to it.
+    pub fn build_map(&self) -> Map {
+        let board = &self.board;
+        let mut map = match board.shape {
+            BoardShape::Rectangle { width, height } => Map::new(board.x, board.y, width, height),
+            BoardShape::Hexagon { side } => Map::new_hexagon(board.x, board.y, side),
+            BoardShape::Mask {
+                ref rows,
+                geometry,
+            } => Map::from_mask(board.x, board.y, rows, geometry),
+        };
+        map.free_placement = self.free_placement;
+        for &(x, y) in board.blocked.iter() {
+            if map.xy_isin(x, y) {
+                let idx = map.xy_idx(x, y);
+                map.map[idx] = WALL;
+            }
+        }
+        map
+    }
+}
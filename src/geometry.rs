@@ -0,0 +1,124 @@
+use rltk::Point;
+use serde::{Deserialize, Serialize};
+
+const LINE_NEIGHBORS: [(i32, i32); 4] = [(-1, 0), (0, -1), (1, 0), (0, 1)];
+const EDGE_NEIGHBORS: [(i32, i32); 4] = [(-1, -1), (1, -1), (1, 1), (-1, 1)];
+
+// Triangles alternate up and down along a row: (x, y) points up when x + y is even.
+const UP_LINE_NEIGHBORS: [(i32, i32); 3] = [(-1, 0), (1, 0), (0, 1)];
+const DOWN_LINE_NEIGHBORS: [(i32, i32); 3] = [(-1, 0), (1, 0), (0, -1)];
+const UP_EDGE_NEIGHBORS: [(i32, i32); 9] = [
+    (-2, 0),
+    (2, 0),
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+    (-2, 1),
+    (-1, 1),
+    (1, 1),
+    (2, 1),
+];
+const DOWN_EDGE_NEIGHBORS: [(i32, i32); 9] = [
+    (-2, 0),
+    (2, 0),
+    (-1, 1),
+    (0, 1),
+    (1, 1),
+    (-2, -1),
+    (-1, -1),
+    (1, -1),
+    (2, -1),
+];
+
+const TRIANGLE_HEIGHT: f64 = 0.866_025_403_784_438_6;
+
+/// Shape of the cells of the board and the pieces.
+#[derive(PartialEq, Copy, Clone, Serialize, Deserialize, Debug)]
+pub enum Geometry {
+    Square,
+    Triangle,
+}
+
+impl Default for Geometry {
+    fn default() -> Self {
+        Geometry::Square
+    }
+}
+
+impl Geometry {
+    pub fn is_up(p: Point) -> bool {
+        (p.x + p.y).rem_euclid(2) == 0
+    }
+
+    /// Cells sharing a side with `p`.
+    pub fn line_neighbors(&self, p: Point) -> &'static [(i32, i32)] {
+        match self {
+            Geometry::Square => &LINE_NEIGHBORS,
+            Geometry::Triangle if Geometry::is_up(p) => &UP_LINE_NEIGHBORS,
+            Geometry::Triangle => &DOWN_LINE_NEIGHBORS,
+        }
+    }
+
+    /// Cells sharing only a corner with `p`.
+    pub fn edge_neighbors(&self, p: Point) -> &'static [(i32, i32)] {
+        match self {
+            Geometry::Square => &EDGE_NEIGHBORS,
+            Geometry::Triangle if Geometry::is_up(p) => &UP_EDGE_NEIGHBORS,
+            Geometry::Triangle => &DOWN_EDGE_NEIGHBORS,
+        }
+    }
+
+    /// Rotates a cell by the smallest step of the geometry (90° or 60°).
+    pub fn rotate(&self, p: Point, right: bool) -> Point {
+        match self {
+            Geometry::Square if right => Point::new(-p.y, p.x),
+            Geometry::Square => Point::new(p.y, -p.x),
+            Geometry::Triangle => {
+                // Rotate the centroid around the apex of the triangle at the origin.
+                let angle = if right { 60f64 } else { -60f64 }.to_radians();
+                let (x, y) = Geometry::triangle_center(p);
+                let x = x - 0.5;
+                Geometry::triangle_at(
+                    0.5 + x * angle.cos() - y * angle.sin(),
+                    x * angle.sin() + y * angle.cos(),
+                )
+            }
+        }
+    }
+
+    pub fn flip(&self, p: Point) -> Point {
+        Point::new(-p.x, p.y)
+    }
+
+    /// Cursor movement for one key press. Triangles can only move to a cell
+    /// pointing the same way, so vertical moves zigzag to stay in place.
+    pub fn step(&self, delta_x: i32, delta_y: i32, at: Point) -> (i32, i32) {
+        match self {
+            Geometry::Square => (delta_x, delta_y),
+            Geometry::Triangle if delta_y == 0 => (delta_x * 2, 0),
+            Geometry::Triangle if at.y.rem_euclid(2) == 0 => (1, delta_y),
+            Geometry::Triangle => (-1, delta_y),
+        }
+    }
+
+    pub fn glyph(&self, p: Point) -> u16 {
+        match self {
+            Geometry::Square => rltk::to_cp437('■'),
+            Geometry::Triangle if Geometry::is_up(p) => rltk::to_cp437('▲'),
+            Geometry::Triangle => rltk::to_cp437('▼'),
+        }
+    }
+
+    fn triangle_center(p: Point) -> (f64, f64) {
+        let offset = if Geometry::is_up(p) { 2. / 3. } else { 1. / 3. };
+        (
+            p.x as f64 * 0.5 + 0.5,
+            (p.y as f64 + offset) * TRIANGLE_HEIGHT,
+        )
+    }
+
+    fn triangle_at(x: f64, y: f64) -> Point {
+        let row = (y / TRIANGLE_HEIGHT + 1e-9).floor();
+        Point::new((x * 2. - 1.).round() as i32, row as i32)
+    }
+}
@@ -15,6 +15,17 @@ pub enum Input {
     Cancel,
     Enter,
     Undo,
+    ApproveUndo,
+    RejectUndo,
+    SaveRecord,
+    ToggleAnchors,
+    ToggleAnalysis,
+    CycleHeatmap,
+    TogglePlayable,
+    ZoomIn,
+    ZoomOut,
+    /// Selects the piece at this index of the tray.
+    SelectPiece(usize),
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
@@ -0,0 +1,169 @@
+use super::Input;
+use rltk::VirtualKeyCode;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::io::{Error, ErrorKind};
+
+/// Layouts shipped with the game. `--keys <name>` picks one of them.
+const BUILTIN_LAYOUTS: [(&str, &str); 3] = [
+    ("default", include_str!("../keys/default.json")),
+    ("wasd", include_str!("../keys/wasd.json")),
+    ("vim", include_str!("../keys/vim.json")),
+];
+
+/// Inputs that can be bound to keys, in the order the help lists them.
+const ACTIONS: [Input; 20] = [
+    Input::Left,
+    Input::Right,
+    Input::Up,
+    Input::Down,
+    Input::RotateRight,
+    Input::RotateLeft,
+    Input::Flip,
+    Input::Enter,
+    Input::Cancel,
+    Input::GiveUp,
+    Input::Undo,
+    Input::ApproveUndo,
+    Input::RejectUndo,
+    Input::SaveRecord,
+    Input::ToggleAnchors,
+    Input::ToggleAnalysis,
+    Input::CycleHeatmap,
+    Input::TogglePlayable,
+    Input::ZoomIn,
+    Input::ZoomOut,
+];
+
+macro_rules! key_names {
+    ($($key:ident),* $(,)?) => {
+        [$((stringify!($key), VirtualKeyCode::$key)),*]
+    };
+}
+
+/// Keys that can be bound, by the name used in the files.
+const KEYS: [(&str, VirtualKeyCode); 70] = key_names![
+    A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V, W, X, Y, Z,
+    Key0, Key1, Key2, Key3, Key4, Key5, Key6, Key7, Key8, Key9,
+    Numpad0, Numpad1, Numpad2, Numpad3, Numpad4, Numpad5, Numpad6, Numpad7, Numpad8, Numpad9,
+    Left, Right, Up, Down, Return, Escape, Space, Tab, Back, Delete, Insert, Home, End,
+    PageUp, PageDown, Comma, Period, Slash, Semicolon, Apostrophe, Minus, Equals,
+    LBracket, RBracket,
+];
+
+/// The key of a name used in the files.
+pub fn key_code(name: &str) -> Option<VirtualKeyCode> {
+    KEYS.iter()
+        .find(|(key, _)| *key == name)
+        .map(|(_, code)| *code)
+}
+
+#[derive(Deserialize)]
+struct KeyBindingsDef {
+    /// Built-in layout the bindings start from. Actions listed in `bindings`
+    /// replace its keys.
+    #[serde(default)]
+    layout: Option<String>,
+    /// Keys of each action, by the name of the action.
+    #[serde(default)]
+    bindings: HashMap<String, Vec<String>>,
+}
+
+/// Which keys give which input. Each action may have several keys, the first of
+/// which is shown in the help.
+#[derive(Clone, Debug)]
+pub struct KeyBindings {
+    bindings: Vec<(Input, Vec<VirtualKeyCode>)>,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        KeyBindings::builtin("default").unwrap()
+    }
+}
+
+impl KeyBindings {
+    pub fn builtin(name: &str) -> Option<Self> {
+        BUILTIN_LAYOUTS
+            .iter()
+            .find(|(builtin, _)| *builtin == name)
+            .map(|(_, json)| Self::parse(json).expect("Broken built-in key bindings."))
+    }
+
+    pub fn load(path: &str) -> std::io::Result<Self> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    fn parse(json: &str) -> std::io::Result<Self> {
+        let def: KeyBindingsDef =
+            serde_json::from_str(json).map_err(|err| Error::new(ErrorKind::InvalidData, err))?;
+        Self::build(def).map_err(|err| Error::new(ErrorKind::InvalidData, err))
+    }
+
+    fn build(def: KeyBindingsDef) -> Result<Self, String> {
+        let mut bindings = match &def.layout {
+            Some(layout) => {
+                KeyBindings::builtin(layout)
+                    .ok_or_else(|| format!("Unknown layout: {}", layout))?
+                    .bindings
+            }
+            None => ACTIONS.iter().map(|action| (action.clone(), Vec::new())).collect(),
+        };
+
+        for (action, names) in def.bindings.iter() {
+            let slot = bindings
+                .iter_mut()
+                .find(|(input, _)| format!("{:?}", input) == *action)
+                .ok_or_else(|| format!("Unknown action: {}", action))?;
+            slot.1 = names
+                .iter()
+                .map(|name| key_code(name).ok_or_else(|| format!("Unknown key: {}", name)))
+                .collect::<Result<_, _>>()?;
+        }
+
+        let mut owners: HashMap<VirtualKeyCode, &Input> = HashMap::new();
+        for (input, keys) in bindings.iter() {
+            for key in keys.iter() {
+                if let Some(other) = owners.insert(*key, input) {
+                    if other != input {
+                        return Err(format!(
+                            "{:?} is bound to both {:?} and {:?}",
+                            key, other, input
+                        ));
+                    }
+                }
+            }
+        }
+        Ok(KeyBindings { bindings })
+    }
+
+    pub fn input(&self, key: Option<VirtualKeyCode>) -> Option<Input> {
+        let key = key?;
+        self.bindings
+            .iter()
+            .find(|(_, keys)| keys.contains(&key))
+            .map(|(input, _)| input.clone())
+    }
+
+    /// The key shown in the help for the input, or "-" when it has none.
+    pub fn label(&self, input: Input) -> String {
+        let key = self
+            .bindings
+            .iter()
+            .find(|(action, _)| *action == input)
+            .and_then(|(_, keys)| keys.first());
+        match key {
+            None => "-".to_string(),
+            Some(VirtualKeyCode::Return) => "Enter".to_string(),
+            Some(VirtualKeyCode::Escape) => "Esc".to_string(),
+            Some(VirtualKeyCode::Back) => "Backspace".to_string(),
+            Some(key) => {
+                let name = format!("{:?}", key);
+                match name.strip_prefix("Key") {
+                    Some(digit) => digit.to_string(),
+                    None => name.replace("Numpad", "Num"),
+                }
+            }
+        }
+    }
+}
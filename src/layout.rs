@@ -0,0 +1,245 @@
+use super::{GameModeDef, Geometry, Map, Mode, Player, Polynomio, Position, Screen};
+use rltk::Point;
+use specs::prelude::*;
+
+/// Columns left of the board for the list of moves.
+pub const HISTORY_WIDTH: i32 = 25;
+/// Rows under the board for the heatmap legend and the puzzle timer.
+const BOARD_FOOTER: i32 = 4;
+/// Rows under everything for the two lines of help and a notice.
+const HELP_HEIGHT: i32 = 6;
+/// The stats and the analysis of a player are printed over this many columns
+/// at the top of their tray.
+const STATS_WIDTH: i32 = 63;
+/// Scales of the console, from the natural size up.
+const ZOOM_LEVELS: [f32; 5] = [1.0, 1.25, 1.5, 1.75, 2.0];
+
+/// Where the board, the trays and the help are on the screen, computed from the
+/// size of the console. Each window lays itself out, so it is not synced.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Layout {
+    /// Console size in cells the layout was computed for.
+    pub screen: (i32, i32),
+    /// Index in `ZOOM_LEVELS`.
+    pub zoom: usize,
+    /// First row of the help under the board.
+    pub help_y: i32,
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Layout {
+            screen: (0, 0),
+            zoom: 0,
+            help_y: 60,
+        }
+    }
+}
+
+impl Layout {
+    pub fn zoom_in(&mut self) {
+        self.zoom = (self.zoom + 1).min(ZOOM_LEVELS.len() - 1);
+    }
+
+    pub fn zoom_out(&mut self) {
+        self.zoom = self.zoom.saturating_sub(1);
+    }
+
+    /// Console size in cells that shows the whole game mode without zoom.
+    pub fn preferred_size(game_mode: &GameModeDef) -> (i32, i32) {
+        let map = game_mode.build_map();
+        let piece_set = game_mode.piece_set().expect("Invalid piece set.");
+        let tray = tray_size(
+            piece_set
+                .layout()
+                .into_iter()
+                .zip(piece_set.pieces.iter())
+                .flat_map(|(at, piece)| {
+                    piece
+                        .cells
+                        .iter()
+                        .map(move |cell| (at.0 + cell.0, at.1 + cell.1))
+                }),
+        );
+        let board = (map.width as i32, map.height as i32);
+        let (width, height) = stacked_size(board, tray, game_mode.players.len());
+        // The results screen needs the natural size.
+        (width.max(72), height.max(64))
+    }
+}
+
+/// Size of a tray holding the stats line and pieces covering the cells,
+/// relative to the top left of the tray.
+fn tray_size(cells: impl Iterator<Item = (i32, i32)>) -> (i32, i32) {
+    cells.fold((STATS_WIDTH, 2), |(width, height), (x, y)| {
+        (width.max(x + 1), height.max(y + 1))
+    })
+}
+
+/// History and board with the rows under it.
+fn board_block(board: (i32, i32)) -> (i32, i32) {
+    (HISTORY_WIDTH + board.0, board.1 + BOARD_FOOTER)
+}
+
+/// Half of the trays above the board and the other half under it.
+fn stacked_size(board: (i32, i32), tray: (i32, i32), players: usize) -> (i32, i32) {
+    let block = board_block(board);
+    (
+        block.0.max(tray.0) + 2,
+        players as i32 * (tray.1 + 1) + block.1 + 1 + HELP_HEIGHT + 2,
+    )
+}
+
+/// The board on the left and every tray in a column on its right.
+fn side_size(board: (i32, i32), tray: (i32, i32), players: usize) -> (i32, i32) {
+    let block = board_block(board);
+    let trays = players as i32 * (tray.1 + 1);
+    (
+        block.0 + 2 + tray.0 + 2,
+        block.1.max(trays) + HELP_HEIGHT + 2,
+    )
+}
+
+/// Top left of the board and of every tray, and the first row of the help.
+fn arrange(
+    screen: (i32, i32),
+    board: (i32, i32),
+    tray: (i32, i32),
+    players: usize,
+) -> (Point, Vec<Point>, i32) {
+    let fits = |size: (i32, i32)| size.0 <= screen.0 && size.1 <= screen.1;
+    let stacked = stacked_size(board, tray, players);
+    let side = side_size(board, tray, players);
+    let block = board_block(board);
+
+    if !fits(stacked) && fits(side) {
+        let left = (screen.0 - side.0) / 2 + 1;
+        let top = (screen.1 - side.1) / 2 + 1;
+        let board_at = Point::new(left + HISTORY_WIDTH, top + 1);
+        let trays = (0..players as i32)
+            .map(|i| Point::new(left + block.0 + 2, top + i * (tray.1 + 1)))
+            .collect();
+        let height = block.1.max(players as i32 * (tray.1 + 1));
+        return (board_at, trays, top + height);
+    }
+
+    let width = stacked.0 - 2;
+    let left = ((screen.0 - stacked.0) / 2).max(0) + 1;
+    let top = ((screen.1 - stacked.1) / 2).max(0) + 1;
+    let tray_x = left + (width - tray.0) / 2;
+    let above = (players + 1) / 2;
+
+    let mut y = top;
+    let mut trays = Vec::new();
+    for _ in 0..above {
+        trays.push(Point::new(tray_x, y));
+        y += tray.1 + 1;
+    }
+    let board_at = Point::new(left + (width - block.0) / 2 + HISTORY_WIDTH, y + 1);
+    y += block.1 + 1;
+    for _ in above..players {
+        trays.push(Point::new(tray_x, y));
+        y += tray.1 + 1;
+    }
+    (board_at, trays, y)
+}
+
+/// Lays the game out again when the console has changed size, the zoom has
+/// changed or the positions were reset to those of the game mode, by moving the
+/// board and the trays with the pieces on them. Returns whether anything moved.
+pub fn relayout(ecs: &World, screen: &mut impl Screen) -> bool {
+    let zoom = ZOOM_LEVELS[ecs.fetch::<Layout>().zoom];
+    let (size, visible) = screen.size(zoom);
+    lay_out(ecs, size, visible)
+}
+
+/// Moves the board and the trays to fit in the `visible` part of the console.
+fn lay_out(ecs: &World, screen: (i32, i32), visible: (i32, i32)) -> bool {
+    let mut map = ecs.fetch_mut::<Map>();
+    let player_entities = ecs.fetch::<Vec<Entity>>();
+    let players = ecs.read_storage::<Player>();
+    let mut positions = ecs.write_storage::<Position>();
+    let polynomios = ecs.read_storage::<Polynomio>();
+
+    let mut cells = Vec::new();
+    for (e, player) in player_entities
+        .iter()
+        .filter_map(|e| Some((e, players.get(*e)?)))
+    {
+        let origin = positions.get(*e).unwrap();
+        for piece in player.polynomios.iter() {
+            let position = positions.get(*piece).unwrap();
+            for cell in polynomios.get(*piece).unwrap().shape.cells(0) {
+                cells.push((
+                    position.orig_x - origin.x + cell.x,
+                    position.orig_y - origin.y + cell.y,
+                ));
+            }
+        }
+    }
+    let tray = tray_size(cells.into_iter());
+    let board = (map.width as i32, map.height as i32);
+    let (board_at, trays, help_y) = arrange(visible, board, tray, player_entities.len());
+
+    let mut board_delta = (board_at.x - map.x, board_at.y - map.y);
+    // Triangles point the way the parity of their screen position says.
+    if map.geometry == Geometry::Triangle && (board_delta.0 + board_delta.1) % 2 != 0 {
+        board_delta.0 += 1;
+    }
+    map.x += board_delta.0;
+    map.y += board_delta.1;
+    let mut moved = board_delta != (0, 0);
+
+    let mode = *ecs.fetch::<Mode>();
+    let active_player_id = *ecs.fetch::<usize>();
+    let mut ghosts = Vec::new();
+    for (id, e) in player_entities.iter().enumerate() {
+        let player = match players.get(*e) {
+            Some(player) => player,
+            None => continue,
+        };
+        let origin = positions.get_mut(*e).unwrap();
+        let delta = (trays[id].x - origin.x, trays[id].y - origin.y);
+        if delta == (0, 0) && board_delta == (0, 0) {
+            continue;
+        }
+        moved |= delta != (0, 0);
+        shift(origin, delta, delta);
+
+        for (i, piece) in player.polynomios.iter().enumerate() {
+            let in_hand = mode == Mode::Put && id == active_player_id && i == player.select;
+            let position = positions.get_mut(*piece).unwrap();
+            let name = polynomios.get(*piece).unwrap().name.clone();
+            ghosts.push((name, position.orig_x, position.orig_y, delta));
+            let on_board = player.fixed[i] || in_hand;
+            shift(position, if on_board { board_delta } else { delta }, delta);
+        }
+    }
+
+    // The outlines left in the trays are not tied to a player: they are found by
+    // the name and the tray position of their piece.
+    for (polynomio, position) in (&polynomios, &mut positions).join() {
+        if !polynomio.bg {
+            continue;
+        }
+        let ghost = ghosts.iter().find(|(name, x, y, _)| {
+            *name == polynomio.name && *x == position.orig_x && *y == position.orig_y
+        });
+        if let Some((_, _, _, delta)) = ghost {
+            shift(position, *delta, *delta);
+        }
+    }
+
+    let mut layout = ecs.fetch_mut::<Layout>();
+    moved |= layout.screen != screen || layout.help_y != help_y;
+    layout.screen = screen;
+    layout.help_y = help_y;
+    moved
+}
+
+fn shift(position: &mut Position, delta: (i32, i32), orig_delta: (i32, i32)) {
+    position.x += delta.0;
+    position.y += delta.1;
+    position.orig_x += orig_delta.0;
+    position.orig_y += orig_delta.1;
+}
@@ -0,0 +1,35 @@
+use std::fmt::Arguments;
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Where the log goes while the terminal shows the game.
+pub const LOG_FILE: &str = "blokus.log";
+
+static TO_FILE: AtomicBool = AtomicBool::new(false);
+
+/// Sends the log to `LOG_FILE` instead of stdout, so that it does not draw
+/// over the game in the terminal.
+pub fn log_to_file() {
+    TO_FILE.store(true, Ordering::Relaxed);
+}
+
+pub fn write_log(args: Arguments) {
+    if !TO_FILE.load(Ordering::Relaxed) {
+        println!("{}", args);
+        return;
+    }
+    let file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(LOG_FILE);
+    if let Ok(mut file) = file {
+        let _ = writeln!(file, "{}", args);
+    }
+}
+
+/// Like `println!`, to stdout or to the log file.
+macro_rules! log {
+    ($($arg:tt)*) => {
+        $crate::write_log(format_args!($($arg)*))
+    };
+}
@@ -1,13 +1,17 @@
 use actix_web::web::Data;
 use clap::{App, AppSettings, Arg, SubCommand};
 use rand::prelude::*;
-use rltk::{GameState, Point, Rltk, RGB};
+use rltk::RGB;
 use serde::{Deserialize, Serialize};
 use specs::prelude::*;
 use specs::saveload::{MarkedBuilder, SimpleMarker, SimpleMarkerAllocator};
 use std::sync::Arc;
 use std::sync::Mutex;
 
+#[macro_use]
+mod logging;
+pub use logging::*;
+
 mod components;
 pub use components::*;
 
@@ -47,36 +51,97 @@ pub use entity_vec::*;
 mod client;
 pub use client::*;
 
+mod rules;
+pub use rules::*;
+
+mod geometry;
+pub use geometry::*;
+
+mod record;
+pub use record::*;
+
+mod series;
+pub use series::*;
+
+mod seating;
+pub use seating::*;
+
+mod game_mode;
+pub use game_mode::*;
+
+mod piece_set;
+pub use piece_set::*;
+
+mod movegen;
+pub use movegen::*;
+
+mod solver;
+pub use solver::*;
+
+mod puzzle;
+pub use puzzle::*;
+
+mod shape;
+pub use shape::*;
+
+mod bitboard;
+pub use bitboard::*;
+
+mod bench;
+pub use bench::*;
+
+mod analysis;
+pub use analysis::*;
+
+mod mouse;
+pub use mouse::*;
+
+mod key_bindings;
+pub use key_bindings::*;
+
+mod tray;
+pub use tray::*;
+
+mod layout;
+pub use layout::*;
+
+mod screen;
+pub use screen::*;
+
+mod tty;
+pub use tty::*;
+
 pub struct State {
     pub ecs: World,
-    pub winner: usize,
     pub ism: Data<Mutex<InputQueue>>,
     pub my_player_id: i32,
     pub use_local_input: bool,
-    pub event_history: Vec<Box<dyn Event>>,
     pub broadcast: Option<Data<Mutex<BroadCastTarget>>>,
     pub slot_manager: Option<Data<Mutex<PlayerSlotManager>>>,
     pub pending_broadcast: bool,
+    pub starting_player: usize,
+    pub series_file: Option<String>,
 }
 
 impl State {
     pub fn new(
-        game_mode: &str,
+        game_mode: &GameModeDef,
         ism: Data<Mutex<InputQueue>>,
         my_player_id: i32,
         use_local_input: bool,
         broadcast: Option<Data<Mutex<BroadCastTarget>>>,
         slot_manager: Option<Data<Mutex<PlayerSlotManager>>>,
+        rules: Rules,
     ) -> Self {
         let mut state = State {
             ecs: World::new(),
-            winner: 0,
             ism: ism.clone(),
             my_player_id: my_player_id,
             use_local_input: use_local_input,
-            event_history: Vec::new(),
             broadcast: broadcast,
             pending_broadcast: false,
+            starting_player: 0,
+            series_file: None,
             slot_manager,
         };
         state.ecs.register::<Position>();
@@ -87,13 +152,20 @@ impl State {
         state.ecs.register::<SerializeHelper>();
 
         state.ecs.insert(SimpleMarkerAllocator::<SyncOnline>::new());
-
-        match game_mode {
-            "normal" => state.prepare_4players_game(),
-            "duo" => state.prepare_2players_game(),
-            "debug" => state.prepare_game_small(),
-            _ => {}
-        };
+        state.ecs.insert(rules);
+        state.ecs.insert(None as Option<UndoRequest>);
+        state.ecs.insert(Vec::<GameEvent>::new());
+        state.ecs.insert(None as Option<Notice>);
+        state.ecs.insert(Overlays::default());
+        state.ecs.insert(AnalysisCache::default());
+        state.ecs.insert(HeatmapCache::default());
+        state.ecs.insert(Mouse::default());
+        state.ecs.insert(KeyBindings::default());
+        state.ecs.insert(Layout::default());
+        state.ecs.insert(None as Option<Series>);
+        state.ecs.insert(None as Option<Puzzle>);
+
+        state.prepare_game(game_mode);
 
         state
     }
@@ -106,19 +178,51 @@ impl State {
         self.ism.lock().unwrap().pop()
     }
 
-    pub fn push_event(&mut self, event: Box<dyn Event>) {
-        self.event_history.push(event);
+    pub fn push_event(&mut self, event: GameEvent) {
+        self.ecs.fetch_mut::<Vec<GameEvent>>().push(event);
+        *self.ecs.fetch_mut::<Option<UndoRequest>>() = None;
+        *self.ecs.fetch_mut::<Option<Notice>>() = None;
     }
 
     pub fn undo(&mut self) {
-        if let Some(mut event) = self.event_history.pop() {
-            (*event).undo(self);
+        let event = self.ecs.fetch_mut::<Vec<GameEvent>>().pop();
+        if let Some(event) = event {
+            event.undo(&self.ecs);
             if event.should_chain_next() {
                 self.undo();
             }
         }
     }
 
+    /// Seat that made the last move.
+    pub fn last_mover(&self) -> Option<i32> {
+        self.ecs
+            .fetch::<Seating>()
+            .last_mover(&self.ecs.fetch::<Vec<GameEvent>>())
+            .map(|seat| seat as i32)
+    }
+
+    /// Seat whose turn it is.
+    pub fn active_seat(&self) -> i32 {
+        let active_player_id = *self.ecs.fetch::<usize>();
+        self.ecs
+            .fetch::<Seating>()
+            .controller(active_player_id, &self.ecs.fetch::<Vec<GameEvent>>()) as i32
+    }
+
+    pub fn undo_voters(&self, requester: i32) -> Vec<i32> {
+        match &self.slot_manager {
+            Some(slot_manager) => {
+                let mut sm = slot_manager.lock().unwrap();
+                (0..sm.len())
+                    .filter(|&id| id as i32 != requester && sm.get(id).is_some())
+                    .map(|id| id as i32)
+                    .collect()
+            }
+            None => Vec::new(),
+        }
+    }
+
     pub fn broadcast(&mut self, trigger: UserInput) {
         if let Some(broadcast) = &self.broadcast {
             if let Some(addr) = &broadcast.lock().unwrap().addr {
@@ -142,19 +246,32 @@ pub enum Mode {
     Finish,
 }
 
-impl GameState for State {
-    fn tick(&mut self, ctx: &mut Rltk) {
+impl Game for State {
+    fn ecs(&self) -> &World {
+        &self.ecs
+    }
+
+    fn local_turn(&self) -> bool {
+        self.use_local_input || self.active_seat() == self.my_player_id
+    }
+
+    fn frame<S: Screen>(&mut self, ctx: &mut S, inputs: Vec<Input>, frame_time_ms: f32) {
         let mut updated = false;
         if let Some(slot_maneger) = &self.slot_manager {
             let mut sm = slot_maneger.lock().unwrap();
             if sm.consume_updated() {
+                let seating = self.ecs.fetch::<Seating>();
                 let mut players = self.ecs.write_storage::<Player>();
                 let entities = self.ecs.entities();
                 for (_, player) in (&entities, &mut players).join() {
-                    if let Some(slot) = sm.get(player.id as usize) {
+                    let seat = match seating.owner(player.id as usize) {
+                        Owner::Seat(seat) => seat,
+                        Owner::Shared => continue,
+                    };
+                    if let Some(slot) = sm.get(seat) {
                         player.name = Some(slot.name.clone())
                     } else {
-                        player.name = Some(format!("Player #{} (Not connected)", player.id))
+                        player.name = Some(format!("Player #{} (Not connected)", seat))
                     }
                 }
                 updated = true;
@@ -172,7 +289,7 @@ impl GameState for State {
         };
 
         if updated {
-            let mut stats = StatsCollectSystem { winner: 0 };
+            let mut stats = StatsCollectSystem {};
             stats.run_now(&self.ecs);
             render(&self.ecs, ctx, self.slot_manager.clone());
         }
@@ -180,34 +297,94 @@ impl GameState for State {
         let active_player_id = *self.ecs.read_resource::<usize>() as i32;
 
         let host_player_id = if self.use_local_input {
-            active_player_id
+            self.active_seat()
         } else {
             self.my_player_id
         };
-        map_virtual_key_code(ctx.key).map(|i| {
-            self.push_input(UserInput {
-                player_id: host_player_id,
-                input: i,
-                token: Some(0),
-            })
-        });
+        // Only the host starts the rematch, so that the results screen is not
+        // wiped while the others are still reading it.
+        let mut rematch = None;
+        for input in inputs {
+            match input {
+                Input::Enter if *self.ecs.fetch::<Mode>() == Mode::Finish => {
+                    rematch = Some(UserInput {
+                        player_id: host_player_id,
+                        input,
+                        token: Some(0),
+                    });
+                }
+                Input::SaveRecord if *self.ecs.fetch::<Mode>() != Mode::Initialize => {
+                    save_record_with_notice(&mut self.ecs);
+                    render(&self.ecs, ctx, self.slot_manager.clone());
+                }
+                Input::SaveRecord => {}
+                Input::ToggleAnchors => {
+                    self.ecs.fetch_mut::<Overlays>().anchors ^= true;
+                    render(&self.ecs, ctx, self.slot_manager.clone());
+                }
+                Input::ToggleAnalysis => {
+                    self.ecs.fetch_mut::<Overlays>().analysis ^= true;
+                    render(&self.ecs, ctx, self.slot_manager.clone());
+                }
+                Input::CycleHeatmap => {
+                    self.ecs.fetch_mut::<Overlays>().cycle_heatmap();
+                    render(&self.ecs, ctx, self.slot_manager.clone());
+                }
+                Input::TogglePlayable => {
+                    self.ecs.fetch_mut::<Overlays>().playable ^= true;
+                    render(&self.ecs, ctx, self.slot_manager.clone());
+                }
+                Input::ZoomIn => self.ecs.fetch_mut::<Layout>().zoom_in(),
+                Input::ZoomOut => self.ecs.fetch_mut::<Layout>().zoom_out(),
+                i => self.push_input(UserInput {
+                    player_id: host_player_id,
+                    input: i,
+                    token: Some(0),
+                }),
+            }
+        }
 
-        let mut input_result: InputResult = InputResult::Noop;
-
-        loop {
-            let user_input = self.pop_input();
-            if let Some(user_input) = user_input {
-                println!("Input: {:?}", user_input);
-                input_result = player_input(self, user_input);
-                println!("  --> {:?}", input_result);
-                match input_result.clone() {
-                    InputResult::Updated { .. } => input_result = input_result,
-                    InputResult::Noop => {
-                        continue;
-                    }
+        if relayout(&self.ecs, ctx) {
+            render(&self.ecs, ctx, self.slot_manager.clone());
+        }
+
+        let mut timer_ticked = false;
+        {
+            let mode = *self.ecs.fetch::<Mode>();
+            if mode == Mode::Select || mode == Mode::Put {
+                let player_entity = self.ecs.fetch::<Vec<Entity>>()[active_player_id as usize];
+                let mut players = self.ecs.write_storage::<Player>();
+                if let Some(player) = players.get_mut(player_entity) {
+                    let before = player.time_used as i32;
+                    player.time_used += frame_time_ms / 1000.;
+                    // The puzzle timer is shown every second.
+                    timer_ticked = before != player.time_used as i32;
                 }
             }
-            break;
+        }
+        if (timer_ticked && self.ecs.fetch::<Option<Puzzle>>().is_some()) || check_puzzle(&self.ecs) {
+            render(&self.ecs, ctx, self.slot_manager.clone());
+        }
+
+        let mut input_result: InputResult = match rematch {
+            Some(trigger) => {
+                self.reset_game();
+                InputResult::Updated {
+                    newmode: Mode::Select,
+                    trigger: Some(trigger),
+                }
+            }
+            None => InputResult::Noop,
+        };
+
+        while input_result == InputResult::Noop {
+            let user_input = match self.pop_input() {
+                Some(user_input) => user_input,
+                None => break,
+            };
+            log!("Input: {:?}", user_input);
+            input_result = player_input(self, user_input);
+            log!("  --> {:?}", input_result);
         }
 
         let mut polynomio_indexing_system = PolynomioIndexingSystem {};
@@ -215,14 +392,21 @@ impl GameState for State {
 
         match input_result {
             InputResult::Updated { newmode, trigger } => {
-                {
+                let finished_now = {
                     let mut mode = self.ecs.write_resource::<Mode>();
+                    let finished_now = *mode != Mode::Finish && newmode == Mode::Finish;
                     *mode = newmode;
-                }
+                    finished_now
+                };
 
-                let mut stats = StatsCollectSystem { winner: 0 };
+                let mut stats = StatsCollectSystem {};
                 stats.run_now(&self.ecs);
-                self.winner = stats.winner;
+
+                if finished_now {
+                    self.record_series_game();
+                    finish_puzzle(&self.ecs);
+                }
+                self.save_series();
 
                 render(&self.ecs, ctx, self.slot_manager.clone());
 
@@ -249,20 +433,7 @@ pub struct ClientState {
 
 impl ClientState {
     fn new(url: String, player_name: String, player_id: Option<i32>) -> Self {
-        let mut ecs = World::new();
-
-        ecs.register::<Position>();
-        ecs.register::<Polynomio>();
-        ecs.register::<Player>();
-        ecs.register::<SimpleMarker<SyncOnline>>();
-        ecs.register::<SerializeHelper>();
-
-        let empty_players: Vec<Entity> = Vec::new();
-        ecs.insert(SimpleMarkerAllocator::<SyncOnline>::new());
-        ecs.insert(Map::new(0, 0, 1, 1));
-        ecs.insert(empty_players);
-        ecs.insert(0 as usize);
-        ecs.insert(Mode::Initialize);
+        let ecs = empty_world();
 
         let client = Client::new(url.clone(), player_name.clone());
 
@@ -294,24 +465,57 @@ impl ClientState {
     }
 }
 
-impl GameState for ClientState {
-    fn tick(&mut self, ctx: &mut rltk::Rltk) {
-        map_virtual_key_code(ctx.key).map(|i| {
-            if i == Input::Undo || !self.locked {
+impl Game for ClientState {
+    fn ecs(&self) -> &World {
+        &self.ecs
+    }
+
+    fn local_turn(&self) -> bool {
+        let active_player_id = *self.ecs.fetch::<usize>();
+        let active_seat = self
+            .ecs
+            .fetch::<Seating>()
+            .controller(active_player_id, &self.ecs.fetch::<Vec<GameEvent>>());
+        self.player_id == Some(active_seat as i32)
+    }
+
+    fn frame<S: Screen>(&mut self, ctx: &mut S, inputs: Vec<Input>, _frame_time_ms: f32) {
+        for i in inputs {
+            let undo_input = matches!(i, Input::Undo | Input::ApproveUndo | Input::RejectUndo);
+            if i == Input::SaveRecord {
+                if *self.ecs.fetch::<Mode>() != Mode::Initialize {
+                    save_record_with_notice(&mut self.ecs);
+                }
+            } else if i == Input::Enter && *self.ecs.fetch::<Mode>() == Mode::Finish {
+                let notice = Notice("The host starts the next game.".to_string());
+                *self.ecs.fetch_mut::<Option<Notice>>() = Some(notice);
+            } else if i == Input::ToggleAnchors {
+                self.ecs.fetch_mut::<Overlays>().anchors ^= true;
+            } else if i == Input::ToggleAnalysis {
+                self.ecs.fetch_mut::<Overlays>().analysis ^= true;
+            } else if i == Input::CycleHeatmap {
+                self.ecs.fetch_mut::<Overlays>().cycle_heatmap();
+            } else if i == Input::TogglePlayable {
+                self.ecs.fetch_mut::<Overlays>().playable ^= true;
+            } else if i == Input::ZoomIn {
+                self.ecs.fetch_mut::<Layout>().zoom_in();
+            } else if i == Input::ZoomOut {
+                self.ecs.fetch_mut::<Layout>().zoom_out();
+            } else if undo_input || !self.locked {
                 let token: i32 = self.rnd.gen();
                 self.latest_token = token;
-                self.client.send_input(i, token);
+                self.client.send_input(i.clone(), token);
             }
-        });
 
-        let newmode = if self.connecting {
-            Mode::Initialize
-        } else {
-            player_input_client(self, ctx)
-        };
-        {
-            let mut mode = self.ecs.write_resource::<Mode>();
-            *mode = newmode
+            let newmode = if self.connecting {
+                Mode::Initialize
+            } else {
+                player_input_client(self, Some(i))
+            };
+            *self.ecs.write_resource::<Mode>() = newmode;
+        }
+        if self.connecting {
+            *self.ecs.write_resource::<Mode>() = Mode::Initialize;
         }
 
         match self.client.next_message() {
@@ -336,13 +540,22 @@ impl GameState for ClientState {
                             || trigger.input == Input::RequestBroadcast
                             || trigger.token == Some(self.latest_token))
                     {
-                        println!("Applying a game update...");
-                        load_game(&mut self.ecs, &serialized_data);
-                        println!(
-                            "Applied the game update: mode: {:?}, apid: {:?}",
-                            *self.ecs.fetch::<Mode>(),
-                            *self.ecs.fetch::<usize>()
-                        );
+                        log!("Applying a game update...");
+                        let moves = self.ecs.fetch::<Vec<GameEvent>>().len();
+                        // A rejected update leaves the last good state in place.
+                        match load_game(&mut self.ecs, &serialized_data) {
+                            Ok(()) => {
+                                if self.ecs.fetch::<Vec<GameEvent>>().len() != moves {
+                                    *self.ecs.fetch_mut::<Option<Notice>>() = None;
+                                }
+                                log!(
+                                    "Applied the game update: mode: {:?}, apid: {:?}",
+                                    *self.ecs.fetch::<Mode>(),
+                                    *self.ecs.fetch::<usize>()
+                                );
+                            }
+                            Err(err) => log!("[ERROR] Ignored a malformed game update: {}", err),
+                        }
                         self.locked = false;
                     }
                 }
@@ -353,11 +566,95 @@ impl GameState for ClientState {
         if self.connecting {
             ctx.print(5, 5, format!("Connecting to {:?} ...", self.url));
         } else {
+            relayout(&self.ecs, ctx);
             render(&self.ecs, ctx, None);
         }
     }
 }
 
+/// A world holding no game yet, for loading a serialized one into it.
+fn empty_world() -> World {
+    let mut ecs = loadable_world();
+
+    ecs.insert(None as Option<Notice>);
+    ecs.insert(Overlays::default());
+    ecs.insert(AnalysisCache::default());
+    ecs.insert(HeatmapCache::default());
+    ecs.insert(Mouse::default());
+    ecs.insert(KeyBindings::default());
+    ecs.insert(Layout::default());
+    ecs.insert(None as Option<Puzzle>);
+
+    ecs
+}
+
+/// Prints how many cells a player can still place in a saved position, and how.
+fn solve_position(path: &str, player_id: Option<usize>, budget: Budget) {
+    let game = load_position(path).unwrap_or_else(|err| {
+        eprintln!("[ERROR] Failed to load the position from {}: {}", path, err);
+        std::process::exit(1);
+    });
+    let mut ecs = empty_world();
+    if let Err(err) = load_game(&mut ecs, &game) {
+        eprintln!("[ERROR] Failed to load the position from {}: {}", path, err);
+        std::process::exit(1);
+    }
+
+    let player_id = player_id.unwrap_or(*ecs.fetch::<usize>());
+    let player_entities = ecs.fetch::<Vec<Entity>>();
+    let players = ecs.read_storage::<Player>();
+    let polynomios = ecs.read_storage::<Polynomio>();
+    let player = match player_entities.get(player_id).and_then(|e| players.get(*e)) {
+        Some(player) => player,
+        None => {
+            eprintln!("[ERROR] No player #{} in the position", player_id + 1);
+            std::process::exit(1);
+        }
+    };
+    let pieces: Vec<(usize, Shape)> = player
+        .polynomios
+        .iter()
+        .zip(player.fixed.iter())
+        .enumerate()
+        .filter(|(_, (_, fixed))| !**fixed)
+        .map(|(i, (e, _))| (i, polynomios.get(*e).unwrap().shape.clone()))
+        .collect();
+
+    let map = ecs.fetch::<Map>();
+    let solution = solve(&map, player_id as i32, &pieces, budget);
+
+    println!(
+        "{} can place {} of the {} remaining cells.",
+        player.display_name(),
+        solution.placed,
+        solution.remaining
+    );
+    if solution.places_all() {
+        println!("All the remaining pieces can be placed.");
+    } else if solution.exhaustive {
+        println!("The remaining pieces cannot all be placed.");
+    } else {
+        println!("The search was cut by its budget. A better line may exist.");
+    }
+    for m in solution.moves.iter() {
+        let polynomio = polynomios.get(player.polynomios[m.piece]).unwrap();
+        let cells: Vec<String> = m
+            .coods
+            .iter()
+            .map(|c| format!("({},{})", c.x + m.position.x, c.y + m.position.y))
+            .collect();
+        println!("  {}: {}", polynomio.name, cells.join(" "));
+    }
+}
+
+fn save_record_with_notice(ecs: &mut World) {
+    let notice = match save_record(ecs) {
+        Ok(path) => format!("Saved the game record to {}", path),
+        Err(err) => format!("Failed to save the game record: {}", err),
+    };
+    *ecs.fetch_mut::<Option<Notice>>() = Some(Notice(notice));
+}
+
 fn main() -> rltk::BError {
     let matches = App::new("Blokus")
         .version("1.0")
@@ -368,8 +665,76 @@ fn main() -> rltk::BError {
             Arg::with_name("mode")
                 .short("m")
                 .long("mode")
-                .help("Game mode. 'normal': 4-players game 'duo': 2-players game")
-                .possible_values(&["normal", "duo", "debug"])
+                .help("Game mode. 'normal': 4-players game 'duo': 2-players game 'trio': 3-players game with a shared neutral colour 'classic': 2-players game with two colours each 'trigon': 4-players game on a triangular board 'puzzle': place all the pieces alone 'pentomino': fill a 6x10 box with the 12 pentominoes")
+                .possible_values(&["normal", "duo", "trio", "classic", "trigon", "puzzle", "pentomino", "debug"])
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("mode-file")
+                .long("mode-file")
+                .help("Load the game mode from a JSON file instead of a built-in mode")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("keys")
+                .short("k")
+                .long("keys")
+                .help("Key layout. 'default': arrow keys 'wasd': WASD to move, Q/E to rotate 'vim': hjkl to move")
+                .possible_values(&["default", "wasd", "vim"])
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("keys-file")
+                .long("keys-file")
+                .help("Load the key bindings from a JSON file instead of a built-in layout")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("frontend")
+                .short("f")
+                .long("frontend")
+                .help("Where to play. 'window': a window with the mouse 'tty': the terminal, with the keys only")
+                .possible_values(&["window", "tty"])
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("undo")
+                .short("u")
+                .long("undo")
+                .help("Who may undo the last move. 'mover': only the player who made it 'vote': opponents must approve")
+                .possible_values(&["anyone", "mover", "vote", "disabled"])
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("scoring")
+                .short("s")
+                .long("scoring")
+                .help("Scoring rule. 'simple': -1 per remaining square 'standard': +15 for placing all pieces 'advanced': +5 more if the monomino was placed last")
+                .possible_values(&["simple", "standard", "advanced"])
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("ranked")
+                .long("ranked")
+                .help("Ranked game. Undo is disabled")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("rotate-start")
+                .long("rotate-start")
+                .help("Rotate the starting player on every rematch")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("series")
+                .long("series")
+                .help("Play a series of N games with cumulative scores")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("series-file")
+                .long("series-file")
+                .help("Where the series is saved and resumed from. Default: blokus-series.json")
                 .takes_value(true),
         )
         .arg(
@@ -380,6 +745,46 @@ fn main() -> rltk::BError {
                 .takes_value(true),
         )
         .subcommand(SubCommand::with_name("play"))
+        .subcommand(
+            SubCommand::with_name("solve")
+                .about("Finds how many cells a player can still place in a saved game record or series")
+                .arg(Arg::with_name("file").required(true).takes_value(true))
+                .arg(
+                    Arg::with_name("player-id")
+                        .short("p")
+                        .long("player-id")
+                        .help("Player to solve for. Default: the player to move")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("nodes")
+                        .long("nodes")
+                        .help("Stop the search after N positions")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("time")
+                        .long("time")
+                        .help("Stop the search after N seconds")
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("bench")
+                .about("Measures the speed of move generation on random games of the game mode")
+                .arg(
+                    Arg::with_name("games")
+                        .long("games")
+                        .help("Number of games to play. Default: 20")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("seed")
+                        .long("seed")
+                        .help("Seed of the random moves. Default: 0")
+                        .takes_value(true),
+                ),
+        )
         .subcommand(
             SubCommand::with_name("host").arg(
                 Arg::with_name("player-id")
@@ -405,8 +810,39 @@ fn main() -> rltk::BError {
         )
         .get_matches();
 
-    let game_mode = matches.value_of("mode").unwrap_or("normal");
+    let game_mode = match matches.value_of("mode-file") {
+        Some(path) => GameModeDef::load(path).unwrap_or_else(|err| {
+            eprintln!("[ERROR] Failed to load the game mode from {}: {}", path, err);
+            std::process::exit(1);
+        }),
+        None => GameModeDef::builtin(matches.value_of("mode").unwrap_or("normal")).unwrap(),
+    };
+    let key_bindings = match matches.value_of("keys-file") {
+        Some(path) => KeyBindings::load(path).unwrap_or_else(|err| {
+            eprintln!("[ERROR] Failed to load the key bindings from {}: {}", path, err);
+            std::process::exit(1);
+        }),
+        None => KeyBindings::builtin(matches.value_of("keys").unwrap_or("default")).unwrap(),
+    };
     let name = matches.value_of("name").unwrap_or("Anonymous");
+    let undo_policy = UndoPolicy::parse(matches.value_of("undo").unwrap_or("anyone")).unwrap();
+    let scoring = ScoringMode::parse(matches.value_of("scoring").unwrap_or("simple")).unwrap();
+    let rules = Rules::new(
+        undo_policy,
+        matches.is_present("ranked"),
+        scoring,
+        matches.is_present("rotate-start"),
+    );
+    let series = matches.value_of("series").map(|games| match games.parse::<usize>() {
+        Ok(games) if games > 0 => games,
+        _ => {
+            eprintln!("[ERROR] The number of games in a series must be a positive integer");
+            std::process::exit(1);
+        }
+    });
+    let series_file = matches
+        .value_of("series-file")
+        .unwrap_or("blokus-series.json");
 
     if name.len() <= 0 || name.len() > 30 {
         eprintln!("[ERROR] The length of the name must be greater than 1 and less than 31");
@@ -419,20 +855,58 @@ fn main() -> rltk::BError {
         std::process::exit(1);
     }
 
-    use rltk::RltkBuilder;
-    let context = RltkBuilder::simple(72, 64)?.with_title("Blokus").build()?;
+    if let Some(sub_matches) = matches.subcommand_matches("solve") {
+        let number = |name: &str| {
+            sub_matches.value_of(name).map(|n| match n.parse::<usize>() {
+                Ok(n) => n,
+                Err(_) => {
+                    eprintln!("[ERROR] --{} must be a non-negative integer", name);
+                    std::process::exit(1);
+                }
+            })
+        };
+        let budget = Budget {
+            nodes: number("nodes"),
+            time: number("time").map(|secs| std::time::Duration::from_secs(secs as u64)),
+        };
+        solve_position(
+            sub_matches.value_of("file").unwrap(),
+            number("player-id"),
+            budget,
+        );
+        return Ok(());
+    }
+
+    if let Some(sub_matches) = matches.subcommand_matches("bench") {
+        let number = |name: &str, default: u64| match sub_matches.value_of(name) {
+            Some(n) => n.parse::<u64>().unwrap_or_else(|_| {
+                eprintln!("[ERROR] --{} must be a non-negative integer", name);
+                std::process::exit(1);
+            }),
+            None => default,
+        };
+        bench(&game_mode, number("games", 20) as usize, number("seed", 0));
+        return Ok(());
+    }
+
+    let tty = matches.value_of("frontend") == Some("tty");
+    // A joining client learns the game mode from the host and lays itself out then.
+    let size = match matches.subcommand_name() {
+        Some("join") => (72, 64),
+        _ => Layout::preferred_size(&game_mode),
+    };
 
     if let Some(_) = matches.subcommand_matches("play") {
         let ism: Data<Mutex<InputQueue>> = Data::new(Mutex::new(InputQueue::new()));
-        let gs = State::new(game_mode, ism, 0, true, None, None);
+        let mut gs = State::new(&game_mode, ism, 0, true, None, None, rules);
+        gs.ecs.insert(key_bindings);
+        if let Some(games) = series {
+            gs.start_series(games, series_file);
+        }
 
-        rltk::main_loop(context, gs)
+        run(gs, tty, size)
     } else if let Some(ref sub_matches) = matches.subcommand_matches("host") {
-        let n_players = match game_mode {
-            "duo" => 2,
-            "normal" => 4,
-            _ => 4,
-        } as usize;
+        let n_players = game_mode.seats();
 
         let ism: Data<Mutex<InputQueue>> = Data::new(Mutex::new(InputQueue::new()));
         let broadcast: Data<Mutex<BroadCastTarget>> =
@@ -469,29 +943,52 @@ fn main() -> rltk::BError {
             }
         });
 
-        let gs = State::new(
-            game_mode,
+        let mut gs = State::new(
+            &game_mode,
             ism,
             my_player_id,
             false,
             Some(broadcast),
             Some(slot_manager_ref),
+            rules,
         );
+        gs.ecs.insert(key_bindings);
+        if let Some(games) = series {
+            gs.start_series(games, series_file);
+        }
 
-        rltk::main_loop(context, gs)
+        run(gs, tty, size)
     } else if let Some(ref sub_matches) = matches.subcommand_matches("join") {
         let my_player_id = sub_matches
             .value_of("player-id")
             .map(|pid| pid.parse::<i32>().unwrap());
         let url = sub_matches.value_of("url").unwrap_or("localhost:8080/ws/");
 
-        let gs = ClientState::new(url.to_string(), String::from(name), my_player_id);
-        rltk::main_loop(context, gs)
+        let mut gs = ClientState::new(url.to_string(), String::from(name), my_player_id);
+        gs.ecs.insert(key_bindings);
+        run(gs, tty, size)
     } else {
         panic!("Unknown subcommand");
     }
 }
 
+/// Runs the game in the terminal or in a window of the size.
+fn run<G: Game + 'static>(game: G, tty: bool, size: (i32, i32)) -> rltk::BError {
+    if tty {
+        run_tty(game)?;
+        return Ok(());
+    }
+
+    use rltk::RltkBuilder;
+    let context = RltkBuilder::simple(size.0, size.1)?
+        .with_title("Blokus")
+        .with_automatic_console_resize(true)
+        .build()?;
+    // Mouse clicks are read from the event queue.
+    rltk::INPUT.lock().activate_event_queue();
+    rltk::main_loop(context, Window(game))
+}
+
 impl State {
     pub fn change_mode(&mut self, m: Mode) {
         let mut mode = self.ecs.fetch_mut::<Mode>();
@@ -503,9 +1000,9 @@ impl State {
         let player_entities = self.ecs.fetch::<Vec<Entity>>();
         let players = self.ecs.read_storage::<Player>();
 
-        let mut next_player_id = *active_player_id;
-        for _ in 0..player_entities.len() {
-            next_player_id = (next_player_id + 1) % player_entities.len();
+        let seating = self.ecs.fetch::<Seating>();
+
+        for next_player_id in seating.turns_after(*active_player_id) {
             if !players.get(player_entities[next_player_id]).unwrap().end {
                 *active_player_id = next_player_id;
                 return;
@@ -513,262 +1010,195 @@ impl State {
         }
     }
 
-    pub fn is_finished(&self) -> bool {
-        let player_entities = self.ecs.fetch::<Vec<Entity>>();
-        let players = self.ecs.read_storage::<Player>();
-        let mut finished = true;
-        for e in player_entities.iter() {
-            finished &= players.get(*e).unwrap().end;
+    /// Puts every piece back to the trays for a rematch. Seats and names are kept.
+    pub fn reset_game(&mut self) {
+        let turn_order = self.ecs.fetch::<Seating>().turn_order.clone();
+        let series_start = {
+            let mut series = self.ecs.fetch_mut::<Option<Series>>();
+            series.as_mut().map(|series| {
+                if series.is_over() {
+                    *series = Series::new(series.games);
+                }
+                series.starting_player(&turn_order)
+            })
+        };
+        if let Some(series_start) = series_start {
+            self.starting_player = series_start;
+        } else if self.ecs.fetch::<Rules>().rotate_start {
+            self.starting_player = self.ecs.fetch::<Seating>().turns_after(self.starting_player)[0];
         }
-        finished
-    }
-
-    fn prepare_4players_game(&mut self) {
-        let players = vec![
-            self.prepare_player(0, 5, 2, RGB::from_f32(1.0, 0.25, 0.2)),
-            self.prepare_player(1, 5, 10, RGB::from_f32(0.2, 1.0, 0.2)),
-            self.prepare_player(2, 5, 44, RGB::from_f32(1.0, 0.9, 0.2)),
-            self.prepare_player(3, 5, 52, RGB::from_f32(0.2, 0.7, 1.0)),
-        ];
 
-        let mut map = Map::new(27, 20, 22, 22);
         {
-            let mut players_store = self.ecs.write_storage::<Player>();
-            map.bind_left_top(players_store.get_mut(players[0]).unwrap());
-            map.bind_right_top(players_store.get_mut(players[1]).unwrap());
-            map.bind_right_bottom(players_store.get_mut(players[2]).unwrap());
-            map.bind_left_bottom(players_store.get_mut(players[3]).unwrap());
+            let mut map = self.ecs.fetch_mut::<Map>();
+            map.clear();
+
+            let player_entities = self.ecs.fetch::<Vec<Entity>>();
+            let mut players = self.ecs.write_storage::<Player>();
+            let mut positions = self.ecs.write_storage::<Position>();
+            let mut polynomios = self.ecs.write_storage::<Polynomio>();
+            for e in player_entities.iter() {
+                let player = players.get_mut(*e).unwrap();
+                for polynomio_entity in player.polynomios.iter() {
+                    positions.get_mut(*polynomio_entity).unwrap().reset();
+                    polynomios.get_mut(*polynomio_entity).unwrap().reset();
+                }
+                for fixed in player.fixed.iter_mut() {
+                    *fixed = false;
+                }
+                player.select = 0;
+                player.end = false;
+                player.time_used = 0.;
+                player.cursor = map.starts[&player.id];
+            }
         }
 
-        self.ecs.insert(players);
-        self.ecs.insert(map);
-        self.ecs.insert(0 as usize);
-        self.ecs.insert(Mode::Initialize);
+        self.ecs.fetch_mut::<Vec<GameEvent>>().clear();
+        *self.ecs.fetch_mut::<Option<UndoRequest>>() = None;
+        *self.ecs.fetch_mut::<Option<Notice>>() = None;
+        if let Some(puzzle) = self.ecs.fetch_mut::<Option<Puzzle>>().as_mut() {
+            puzzle.reset();
+        }
+        *self.ecs.fetch_mut::<usize>() = self.starting_player;
     }
 
-    fn prepare_2players_game(&mut self) {
-        let players = vec![
-            self.prepare_player(0, 5, 7, RGB::from_f32(1.0, 0.25, 0.2)),
-            self.prepare_player(1, 5, 47, RGB::from_f32(1.0, 0.9, 0.2)),
-        ];
+    /// Starts a series of `games` games, or resumes the one saved in `path`.
+    pub fn start_series(&mut self, games: usize, path: &str) {
+        let series = match SeriesFile::load(path) {
+            Ok(saved) if saved.series.games == games && !saved.series.is_over() => {
+                log!(
+                    "Resuming the series from {} (game {} of {})",
+                    path,
+                    saved.series.played() + 1,
+                    games
+                );
+                if let Some(game) = &saved.game {
+                    if let Err(err) = load_game(&mut self.ecs, game) {
+                        eprintln!("[ERROR] Failed to resume the series from {}: {}", path, err);
+                        std::process::exit(1);
+                    }
+                }
+                saved.series
+            }
+            _ => Series::new(games),
+        };
+        self.starting_player = series.starting_player(&self.ecs.fetch::<Seating>().turn_order);
+        *self.ecs.fetch_mut::<Option<Series>>() = Some(series);
+        self.series_file = Some(path.to_string());
+    }
 
-        let mut map = Map::new(30, 23, 16, 16);
-        {
-            let mut players_store = self.ecs.write_storage::<Player>();
-            map.bind(players_store.get_mut(players[0]).unwrap(), 5, 5);
-            map.bind(players_store.get_mut(players[1]).unwrap(), 10, 10);
+    fn record_series_game(&mut self) {
+        // Scores are kept per seat. Every colour of a seat carries the seat's score.
+        let scores: Vec<i32> = {
+            let seating = self.ecs.fetch::<Seating>();
+            let player_entities = self.ecs.fetch::<Vec<Entity>>();
+            let players = self.ecs.read_storage::<Player>();
+            (0..seating.seats)
+                .map(|seat| match seating.colors_of(seat).first() {
+                    Some(&color) => players.get(player_entities[color]).unwrap().score,
+                    None => 0,
+                })
+                .collect()
+        };
+        if let Some(series) = self.ecs.fetch_mut::<Option<Series>>().as_mut() {
+            series.record(scores);
         }
+    }
 
-        self.ecs.insert(players);
-        self.ecs.insert(map);
-        self.ecs.insert(0 as usize);
-        self.ecs.insert(Mode::Initialize);
+    fn save_series(&mut self) {
+        let path = match &self.series_file {
+            Some(path) => path.clone(),
+            None => return,
+        };
+        let series = match &*self.ecs.fetch::<Option<Series>>() {
+            Some(series) => series.clone(),
+            None => return,
+        };
+        let saved = SeriesFile {
+            series,
+            game: Some(dump_game(&mut self.ecs)),
+        };
+        if let Err(err) = saved.save(&path) {
+            eprintln!("[ERROR] Failed to save the series to {}: {:?}", path, err);
+        }
     }
 
-    #[allow(dead_code)]
-    fn prepare_game_small(&mut self) {
-        let players = vec![
-            self.prepare_player_small(0, 5, 2, RGB::from_f32(1.0, 0.25, 0.2)),
-            self.prepare_player_small(1, 5, 44, RGB::from_f32(1.0, 0.9, 0.2)),
-        ];
+    pub fn is_finished(&self) -> bool {
+        let player_entities = self.ecs.fetch::<Vec<Entity>>();
+        let players = self.ecs.read_storage::<Player>();
+        let mut finished = true;
+        for e in player_entities.iter() {
+            finished &= players.get(*e).unwrap().end;
+        }
+        finished
+    }
 
-        let mut map = Map::new(27, 20, 7, 7);
+    fn prepare_game(&mut self, game_mode: &GameModeDef) {
+        let piece_set = game_mode.piece_set().expect("Invalid piece set.");
+        let mut players = Vec::new();
+        for (id, player) in game_mode.players.iter().enumerate() {
+            let (x, y) = player.tray;
+            let color = game_mode.color(id);
+            players.push(self.prepare_player(id as i32, x, y, color, &piece_set));
+        }
+
+        let mut map = game_mode.build_map();
         {
             let mut players_store = self.ecs.write_storage::<Player>();
-            map.bind(players_store.get_mut(players[0]).unwrap(), 1, 1);
-            map.bind(players_store.get_mut(players[1]).unwrap(), 5, 5);
+            for (def, e) in game_mode.players.iter().zip(players.iter()) {
+                let player = players_store.get_mut(*e).unwrap();
+                player.name = def.name.clone();
+                map.bind(player, def.start.0, def.start.1);
+            }
         }
 
+        if game_mode.puzzle {
+            self.ecs.insert(Some(Puzzle::new(&game_mode.description)));
+        }
+        let seating = game_mode.seating();
+        self.starting_player = seating.turn_order[0];
+        self.ecs.insert(seating);
+        self.ecs.insert(piece_set);
         self.ecs.insert(players);
         self.ecs.insert(map);
-        self.ecs.insert(0 as usize);
+        self.ecs.insert(self.starting_player);
         self.ecs.insert(Mode::Initialize);
     }
 
-    fn prepare_polynomio(&mut self, x: i32, y: i32, coods: &[(i32, i32)], color: RGB) -> Entity {
-        let mut coods_vec = Vec::new();
-        for cood in coods {
-            coods_vec.push(Point::new(cood.0, cood.1));
-        }
+    fn prepare_polynomio(&mut self, name: &str, x: i32, y: i32, shape: Shape, color: RGB) -> Entity {
         self.ecs
             .create_entity()
             .with(Position::new(x, y))
-            .with(Polynomio::new(coods_vec.clone(), color * 0.2, true))
+            .with(Polynomio::new(name.to_string(), shape.clone(), color * 0.2, true))
             .marked::<SimpleMarker<SyncOnline>>()
             .build();
 
         self.ecs
             .create_entity()
             .with(Position::new(x, y))
-            .with(Polynomio::new(coods_vec, color, false))
+            .with(Polynomio::new(name.to_string(), shape, color, false))
             .marked::<SimpleMarker<SyncOnline>>()
             .build()
     }
 
-    fn prepare_player(&mut self, id: i32, x: i32, y: i32, color: RGB) -> Entity {
+    fn prepare_player(
+        &mut self,
+        id: i32,
+        x: i32,
+        y: i32,
+        color: RGB,
+        piece_set: &PieceSet,
+    ) -> Entity {
         let mut ps = Vec::new();
-        const YOFF: i32 = 2;
-
-        ps.push(self.prepare_polynomio(
-            x,
-            y + 2 + YOFF,
-            &[(0, -2), (0, -1), (0, 0), (0, 1), (0, 2)],
-            color,
-        ));
-
-        ps.push(self.prepare_polynomio(
-            x + 2,
-            y + 2 + YOFF,
-            &[(0, -2), (0, -1), (0, 0), (0, 1), (1, -2)],
-            color,
-        ));
-
-        ps.push(self.prepare_polynomio(
-            x + 5,
-            y + 2 + YOFF,
-            &[(0, -2), (0, -1), (0, 0), (0, 1), (1, -1)],
-            color,
-        ));
-
-        ps.push(self.prepare_polynomio(
-            x + 8,
-            y + 2 + YOFF,
-            &[(1, -2), (0, -1), (0, 0), (0, 1), (1, -1)],
-            color,
-        ));
-
-        ps.push(self.prepare_polynomio(
-            x + 12,
-            y + 2 + YOFF,
-            &[(-1, -1), (0, -1), (1, -1), (-1, 0), (-1, 1)],
-            color,
-        ));
-
-        ps.push(self.prepare_polynomio(
-            x + 16,
-            y + 2 + YOFF,
-            &[(-1, 1), (-1, 0), (0, 0), (1, 0), (1, -1)],
-            color,
-        ));
-
-        ps.push(self.prepare_polynomio(
-            x + 20,
-            y + 2 + YOFF,
-            &[(-1, 1), (-1, 0), (0, 0), (0, -1), (1, -1)],
-            color,
-        ));
-        // +
-        ps.push(self.prepare_polynomio(
-            x + 24,
-            y + 2 + YOFF,
-            &[(-1, 0), (0, -1), (0, 0), (1, 0), (0, 1)],
-            color,
-        ));
-
-        // [
-        ps.push(self.prepare_polynomio(
-            x + 27,
-            y + 2 + YOFF,
-            &[(0, -1), (0, 0), (0, 1), (1, -1), (1, 1)],
-            color,
-        ));
-
-        // T
-        ps.push(self.prepare_polynomio(
-            x + 31,
-            y + 2 + YOFF,
-            &[(0, 0), (0, -1), (0, 1), (-1, 1), (1, 1)],
-            color,
-        ));
-
-        ps.push(self.prepare_polynomio(
-            x + 34,
-            y + 2 + YOFF,
-            &[(0, 0), (0, 1), (0, -1), (1, 0), (1, 1)],
-            color,
-        ));
-
-        ps.push(self.prepare_polynomio(
-            x + 38,
-            y + 2 + YOFF,
-            &[(0, 0), (-1, 0), (0, -1), (0, 1), (1, -1)],
-            color,
-        ));
-
-        ps.push(self.prepare_polynomio(
-            x + 42,
-            y + 2 + YOFF,
-            &[(0, 0), (-1, 0), (-1, -1), (0, 1)],
-            color,
-        ));
-
-        ps.push(self.prepare_polynomio(
-            x + 44,
-            y + 2 + YOFF,
-            &[(0, 0), (0, -1), (0, 1), (1, -1)],
-            color,
-        ));
-
-        ps.push(self.prepare_polynomio(
-            x + 47,
-            y + 2 + YOFF,
-            &[(0, 0), (0, -1), (0, 1), (1, 0)],
-            color,
-        ));
-
-        ps.push(self.prepare_polynomio(
-            x + 50,
-            y + 1 + YOFF,
-            &[(0, -1), (0, 0), (0, 1), (0, 2)],
-            color,
-        ));
-
-        ps.push(self.prepare_polynomio(
-            x + 52,
-            y + 1 + YOFF,
-            &[(0, 0), (1, 0), (0, 1), (1, 1)],
-            color,
-        ));
-
-        ps.push(self.prepare_polynomio(x + 55, y + 2 + YOFF, &[(0, -1), (0, 0), (0, 1)], color));
-
-        ps.push(self.prepare_polynomio(x + 58, y + 1 + YOFF, &[(-1, 0), (0, 0), (0, 1)], color));
-
-        ps.push(self.prepare_polynomio(x + 60, y + 2 + YOFF, &[(0, 0), (0, 1)], color));
-
-        ps.push(self.prepare_polynomio(x + 62, y + 2 + YOFF, &[(0, 0)], color));
-
-        let player = self
-            .ecs
-            .create_entity()
-            .with(Player::new(id, ps, color, None))
-            .with(Position::new(x, y))
-            .marked::<SimpleMarker<SyncOnline>>()
-            .build();
-        return player;
-    }
-
-    #[allow(dead_code)]
-    fn prepare_player_small(&mut self, id: i32, x: i32, y: i32, color: RGB) -> Entity {
-        let mut ps = Vec::new();
-        const YOFF: i32 = 2;
-
-        ps.push(self.prepare_polynomio(
-            x + 2,
-            y + 2 + YOFF,
-            &[(0, -2), (0, -1), (0, 0), (0, 1), (1, -2)],
-            color,
-        ));
-
-        ps.push(self.prepare_polynomio(x + 5, y + 2 + YOFF, &[(0, 0)], color));
+        for (id, (piece, tray)) in piece_set.pieces.iter().zip(piece_set.layout()).enumerate() {
+            let shape = piece_set.shape(id);
+            ps.push(self.prepare_polynomio(&piece.name, x + tray.0, y + tray.1, shape, color));
+        }
 
-        let player = self
-            .ecs
+        self.ecs
             .create_entity()
             .with(Player::new(id, ps, color, None))
             .with(Position::new(x, y))
             .marked::<SimpleMarker<SyncOnline>>()
-            .build();
-        return player;
+            .build()
     }
 }
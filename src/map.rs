@@ -1,13 +1,12 @@
-use super::{Player, Polynomio};
+use super::{BitBoard, Board, Geometry, Player, Polynomio};
 use rltk::{Point, RGB};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
 pub const EMPTY: i32 = -1;
 pub const WALL: i32 = -2;
-
-const LINE_NEIGHBORS: [(i32, i32); 4] = [(-1, 0), (0, -1), (1, 0), (0, 1)];
-const EDGE_NEIGHBORS: [(i32, i32); 4] = [(-1, -1), (1, -1), (1, 1), (-1, 1)];
+/// Not part of the board. Nothing is drawn there.
+pub const VOID: i32 = -3;
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct Map {
@@ -18,6 +17,14 @@ pub struct Map {
     pub height: usize,
     pub colors: HashMap<i32, RGB>,
     pub starts: HashMap<i32, Point>,
+    pub geometry: Geometry,
+    /// Pieces may go anywhere they fit, as in a shape-filling puzzle.
+    #[serde(default)]
+    pub free_placement: bool,
+    /// Anchors and side contacts of every player, kept up to date by `put` and
+    /// `remove` once `track` is called. Not synced: rebuilt after loading.
+    #[serde(skip)]
+    bits: Option<BitBoard>,
 }
 
 impl Map {
@@ -40,6 +47,73 @@ impl Map {
             height: height,
             colors: HashMap::new(),
             starts: HashMap::new(),
+            geometry: Geometry::Square,
+            free_placement: false,
+            bits: None,
+        }
+    }
+
+    /// A hexagonal board of triangles with `side` triangles on each edge.
+    pub fn new_hexagon(x: i32, y: i32, side: usize) -> Self {
+        let n = side as i32;
+        // Keeps the left corner on an up triangle whatever the parity of the side.
+        let offset = (n - 1) % 2;
+        let width = (4 * n - 1 + offset) as usize;
+        let height = (2 * n) as usize;
+
+        let mut map = vec![VOID; width * height];
+        for row in 0..2 * n {
+            let (start, len) = if row < n {
+                (offset + n - 1 - row, 2 * n + 1 + 2 * row)
+            } else {
+                (offset + row - n, 6 * n - 1 - 2 * row)
+            };
+            for col in start..start + len {
+                map[(col + row * width as i32) as usize] = EMPTY;
+            }
+        }
+
+        Map {
+            map,
+            x,
+            y,
+            width,
+            height,
+            colors: HashMap::new(),
+            starts: HashMap::new(),
+            geometry: Geometry::Triangle,
+            free_placement: false,
+            bits: None,
+        }
+    }
+
+    /// A board drawn as text: '.' is a cell, '#' an obstacle and any other character a hole.
+    pub fn from_mask(x: i32, y: i32, rows: &[String], geometry: Geometry) -> Self {
+        let width = rows.iter().map(|row| row.chars().count()).max().unwrap_or(0);
+        let height = rows.len();
+
+        let mut map = vec![VOID; width * height];
+        for (row_idx, row) in rows.iter().enumerate() {
+            for (col, c) in row.chars().enumerate() {
+                map[col + row_idx * width] = match c {
+                    '.' => EMPTY,
+                    '#' => WALL,
+                    _ => VOID,
+                };
+            }
+        }
+
+        Map {
+            map,
+            x,
+            y,
+            width,
+            height,
+            colors: HashMap::new(),
+            starts: HashMap::new(),
+            geometry,
+            free_placement: false,
+            bits: None,
         }
     }
 
@@ -49,6 +123,12 @@ impl Map {
         self.colors.insert(player.id, player.color);
         self.starts.insert(player.id, start);
         player.cursor = start;
+        self.track();
+    }
+
+    /// Starts keeping the anchors of every player up to date.
+    pub fn track(&mut self) {
+        self.bits = Some(BitBoard::from_map(self));
     }
 
     pub fn bind_left_top(&mut self, player: &mut Player) {
@@ -67,12 +147,24 @@ impl Map {
         self.bind(player, self.width as i32 - 2, self.height as i32 - 2);
     }
 
+    /// Removes every piece from the board, keeping the walls.
+    pub fn clear(&mut self) {
+        for tile in self.map.iter_mut() {
+            if *tile >= 0 {
+                *tile = EMPTY;
+            }
+        }
+        if self.bits.is_some() {
+            self.track();
+        }
+    }
+
     pub fn get(&self, p: Point) -> i32 {
         self.map[self.point_idx(p)]
     }
 
     pub fn touch_with_line(&self, p: Point, player_id: i32) -> bool {
-        for n in LINE_NEIGHBORS.iter() {
+        for n in self.geometry.line_neighbors(p).iter() {
             let np = Point::new(n.0, n.1) + p;
             if self.point_isin(np) && self.get(np) == player_id {
                 return true;
@@ -82,7 +174,7 @@ impl Map {
     }
 
     pub fn touch_with_edge(&self, p: Point, player_id: i32) -> bool {
-        for n in EDGE_NEIGHBORS.iter() {
+        for n in self.geometry.edge_neighbors(p).iter() {
             let np = Point::new(n.0, n.1) + p;
             if self.point_isin(np) && self.get(np) == player_id {
                 return true;
@@ -92,30 +184,43 @@ impl Map {
     }
 
     pub fn try_put(&mut self, position: Point, polynomio: &Polynomio, player_id: i32) -> bool {
+        if !self.can_put(position, &polynomio.coods, player_id) {
+            return false;
+        }
+        self.put(position, &polynomio.coods, player_id);
+        true
+    }
+
+    /// Whether the cells can be put at `position` under the rules: on empty cells, not
+    /// sharing a side with the player's pieces, and touching one of them by a corner
+    /// (or covering the start cell).
+    pub fn can_put(&self, position: Point, coods: &[Point], player_id: i32) -> bool {
         let mut no_touch_with_line = true;
         let mut touch_with_edge = false;
         let mut include_start_position = false;
 
-        for cood in &polynomio.coods {
+        for cood in coods {
             let p = *cood + position;
             if !self.point_isin(p) || self.get(p) != EMPTY {
                 return false;
             }
             no_touch_with_line &= !self.touch_with_line(p, player_id);
             touch_with_edge |= self.touch_with_edge(p, player_id);
-            include_start_position |= self.starts[&player_id] == p;
+            include_start_position |= self.starts.get(&player_id) == Some(&p);
         }
 
-        if !no_touch_with_line || (!touch_with_edge && !include_start_position) {
-            return false;
-        }
+        self.free_placement
+            || (no_touch_with_line && (touch_with_edge || include_start_position))
+    }
 
-        for cood in &polynomio.coods {
-            let p = *cood + position;
-            let idx = self.point_idx(p);
+    pub fn put(&mut self, position: Point, coods: &[Point], player_id: i32) {
+        for cood in coods {
+            let idx = self.point_idx(*cood + position);
             self.map[idx] = player_id;
         }
-        true
+        if let Some(bits) = &mut self.bits {
+            bits.put(position, coods, player_id);
+        }
     }
 
     pub fn try_remove(&mut self, position: Point, polynomio: &Polynomio, player_id: i32) -> bool {
@@ -125,14 +230,39 @@ impl Map {
                 return false;
             }
         }
+        self.remove(position, &polynomio.coods, player_id);
+        true
+    }
 
-        for cood in &polynomio.coods {
-            let p = *cood + position;
-            let idx = self.point_idx(p);
+    pub fn remove(&mut self, position: Point, coods: &[Point], player_id: i32) {
+        if let Some(bits) = &mut self.bits {
+            bits.remove(position, coods, player_id);
+        }
+        for cood in coods {
+            let idx = self.point_idx(*cood + position);
             self.map[idx] = EMPTY;
         }
+    }
 
-        true
+    /// Empty cells where the next piece of the player can start: the start cell, or
+    /// cells touching the player's pieces only by a corner.
+    pub fn anchors(&self, player_id: i32) -> Vec<Point> {
+        if let Some(bits) = &self.bits {
+            return bits.anchors(player_id);
+        }
+        (0..self.map.len())
+            .filter(|&idx| self.map[idx] == EMPTY)
+            .map(|idx| self.idx_point(idx))
+            .filter(|&p| {
+                self.free_placement
+                    || self.starts.get(&player_id) == Some(&p)
+                    || (self.touch_with_edge(p, player_id) && !self.touch_with_line(p, player_id))
+            })
+            .collect()
+    }
+
+    pub fn empty_cells(&self) -> usize {
+        self.map.iter().filter(|&&tile| tile == EMPTY).count()
     }
 
     pub fn xy_idx(&self, x: i32, y: i32) -> usize {
@@ -0,0 +1,158 @@
+use super::{unplayable_pieces, Input, Map, Mode, Player, Polynomio, Position};
+use rltk::{BEvent, Point, Rltk, INPUT};
+use specs::prelude::*;
+use std::collections::HashSet;
+
+const LEFT_BUTTON: usize = 0;
+const RIGHT_BUTTON: usize = 1;
+const MIDDLE_BUTTON: usize = 2;
+
+/// Where the mouse was last seen, and whether the piece in hand follows it.
+/// Local to each window.
+#[derive(Default)]
+pub struct Mouse {
+    last: Option<Point>,
+    following: bool,
+}
+
+/// Turns the mouse into the inputs the keyboard would give, so that they go through
+/// the same path as keys, over the network too:
+/// - a left click on a piece of the tray selects it and takes it in hand,
+/// - the piece in hand walks to the cell under the mouse, one step per frame,
+/// - a left click on the board puts it there,
+/// - a right click rotates it (left with Shift) and a middle click flips it.
+///
+/// Nothing is sent unless the player to move is played from this window
+/// (`local_turn`). rltk does not report the mouse wheel, so it is not used. Must be
+/// called every frame, as it also empties the event queue.
+pub fn mouse_inputs(ecs: &World, ctx: &Rltk, key_pressed: bool, local_turn: bool) -> Vec<Input> {
+    let clicks = mouse_clicks();
+    let point = ctx.mouse_point();
+    let mut mouse = ecs.fetch_mut::<Mouse>();
+    if key_pressed {
+        mouse.following = false;
+    }
+    if mouse.last != Some(point) {
+        mouse.last = Some(point);
+        mouse.following = true;
+    }
+
+    let mode = *ecs.fetch::<Mode>();
+    if !local_turn || (mode != Mode::Select && mode != Mode::Put) {
+        return Vec::new();
+    }
+    let hidden = if clicks.contains(&LEFT_BUTTON) {
+        unplayable_pieces(ecs)
+    } else {
+        Vec::new()
+    };
+    let map = ecs.fetch::<Map>();
+    let players = ecs.read_storage::<Player>();
+    let positions = ecs.read_storage::<Position>();
+    let polynomios = ecs.read_storage::<Polynomio>();
+    let player_entity = ecs.fetch::<Vec<Entity>>()[*ecs.fetch::<usize>()];
+    let player = players.get(player_entity).unwrap();
+    let in_hand = positions.get(player.polynomios[player.select]).unwrap();
+    let on_board = map.point_isin(point - Point::new(map.x, map.y));
+
+    let mut inputs = Vec::new();
+    for button in clicks {
+        match button {
+            LEFT_BUTTON => {
+                let clicked = (0..player.polynomios.len()).find(|&i| {
+                    let e = player.polynomios[i];
+                    let position = positions.get(e).unwrap();
+                    // The piece in hand has left the tray.
+                    let in_tray = !player.fixed[i] && (mode == Mode::Select || i != player.select);
+                    in_tray
+                        && !hidden.contains(&e)
+                        && polynomios.get(e).unwrap().coods.iter().any(|&cood| {
+                            cood + position.to_point() == point
+                        })
+                });
+                if let Some(i) = clicked {
+                    if mode == Mode::Put {
+                        inputs.push(Input::Cancel);
+                    }
+                    inputs.push(Input::SelectPiece(i));
+                    inputs.push(Input::Enter);
+                } else if mode == Mode::Put && on_board {
+                    inputs.extend(steps_to(in_hand, point, &map));
+                    inputs.push(Input::Enter);
+                    mouse.following = false;
+                }
+            }
+            RIGHT_BUTTON if ctx.shift => inputs.push(Input::RotateLeft),
+            RIGHT_BUTTON => inputs.push(Input::RotateRight),
+            MIDDLE_BUTTON => inputs.push(Input::Flip),
+            _ => {}
+        }
+    }
+
+    if inputs.is_empty() && mode == Mode::Put && mouse.following && on_board {
+        match steps_to(in_hand, point, &map).first() {
+            Some(step) => inputs.push(step.clone()),
+            None => mouse.following = false,
+        }
+    }
+    inputs
+}
+
+/// Buttons pressed since the last frame.
+fn mouse_clicks() -> Vec<usize> {
+    let mut clicks = Vec::new();
+    INPUT.lock().for_each_message(|event| {
+        if let BEvent::MouseClick {
+            button,
+            pressed: true,
+        } = event
+        {
+            clicks.push(button);
+        }
+    });
+    clicks
+}
+
+/// Fewest moves bringing the piece as close as it can get to `target`, made the
+/// same way as the arrow keys move it.
+fn steps_to(from: &Position, target: Point, map: &Map) -> Vec<Input> {
+    let distance = |p: &Position| {
+        let (dx, dy) = (p.x - target.x, p.y - target.y);
+        dx * dx + dy * dy
+    };
+    let moves = [
+        (Input::Up, 0, -1),
+        (Input::Down, 0, 1),
+        (Input::Left, -1, 0),
+        (Input::Right, 1, 0),
+    ];
+
+    // Breadth-first search over the positions the piece can reach, each with the
+    // index of the position it was reached from and the move that got there.
+    let mut reached: Vec<(Position, usize, Input)> = vec![(from.clone(), 0, Input::Enter)];
+    let mut visited = HashSet::new();
+    visited.insert((from.x, from.y));
+    let mut best = 0;
+    let mut i = 0;
+    while i < reached.len() {
+        for (input, dx, dy) in moves.iter() {
+            let mut next = reached[i].0.clone();
+            next.translate_within(*dx, *dy, map);
+            if visited.insert((next.x, next.y)) {
+                if distance(&next) < distance(&reached[best].0) {
+                    best = reached.len();
+                }
+                reached.push((next, i, input.clone()));
+            }
+        }
+        i += 1;
+    }
+
+    let mut steps = Vec::new();
+    while best != 0 {
+        steps.push(reached[best].2.clone());
+        best = reached[best].1;
+    }
+    steps.reverse();
+    steps
+}
@@ -0,0 +1,75 @@
+use super::{Geometry, Map, Shape};
+use rltk::Point;
+
+/// A legal placement of a piece: its cells as oriented, put at `position`.
+#[derive(Clone, Debug)]
+pub struct Move {
+    pub piece: usize,
+    pub orientation: usize,
+    pub coods: Vec<Point>,
+    pub position: Point,
+}
+
+/// What move generation needs to know about a board.
+pub trait Board {
+    fn geometry(&self) -> Geometry;
+    fn can_put(&self, position: Point, coods: &[Point], player_id: i32) -> bool;
+    /// Empty cells where the next piece of the player can start.
+    fn anchors(&self, player_id: i32) -> Vec<Point>;
+}
+
+impl Board for Map {
+    fn geometry(&self) -> Geometry {
+        self.geometry
+    }
+
+    fn can_put(&self, position: Point, coods: &[Point], player_id: i32) -> bool {
+        Map::can_put(self, position, coods, player_id)
+    }
+
+    fn anchors(&self, player_id: i32) -> Vec<Point> {
+        Map::anchors(self, player_id)
+    }
+}
+
+/// Legal moves of the player for the given pieces, in each of their distinct orientations.
+pub fn legal_moves<B: Board>(board: &B, player_id: i32, pieces: &[(usize, &Shape)]) -> Vec<Move> {
+    legal_moves_at(board, player_id, pieces, &board.anchors(player_id))
+}
+
+/// Legal moves covering one of the given cells.
+pub fn legal_moves_at<B: Board>(
+    board: &B,
+    player_id: i32,
+    pieces: &[(usize, &Shape)],
+    anchors: &[Point],
+) -> Vec<Move> {
+    let mut moves = Vec::new();
+    for (piece, shape) in pieces.iter() {
+        for orientation in shape.distinct() {
+            let coods = shape.cells(orientation);
+            let mut positions: Vec<Point> = Vec::new();
+            for anchor in anchors.iter() {
+                for cood in coods.iter() {
+                    let position = *anchor - *cood;
+                    // A triangle moved by an odd offset would be turned over.
+                    if board.geometry() == Geometry::Triangle
+                        && (position.x + position.y).rem_euclid(2) != 0
+                    {
+                        continue;
+                    }
+                    if !positions.contains(&position) && board.can_put(position, coods, player_id) {
+                        positions.push(position);
+                    }
+                }
+            }
+            moves.extend(positions.into_iter().map(|position| Move {
+                piece: *piece,
+                orientation,
+                coods: coods.to_vec(),
+                position,
+            }));
+        }
+    }
+    moves
+}
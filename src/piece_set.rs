@@ -0,0 +1,149 @@
+use super::{Geometry, Shape};
+use rltk::Point;
+use serde::{Deserialize, Serialize};
+
+/// Piece sets shipped with the game. A mode refers to them by name.
+const BUILTIN_PIECE_SETS: [(&str, &str); 3] = [
+    ("standard", include_str!("../pieces/standard.json")),
+    ("small", include_str!("../pieces/small.json")),
+    ("trigon", include_str!("../pieces/trigon.json")),
+];
+
+/// Width of the tray where pieces without a `tray` position are laid out.
+const TRAY_WIDTH: i32 = 64;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PieceDef {
+    pub name: String,
+    /// Cells of the piece. The piece rotates around (0, 0), which is an up
+    /// triangle for triangular pieces.
+    pub cells: Vec<(i32, i32)>,
+    /// Where (0, 0) of the piece lies in the tray, relative to the player.
+    /// Pieces without a position are laid out in rows after the others.
+    #[serde(default)]
+    pub tray: Option<(i32, i32)>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PieceSet {
+    pub geometry: Geometry,
+    pub pieces: Vec<PieceDef>,
+}
+
+/// The `pieces` of a game mode: the name of a built-in set, or the set itself.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(untagged)]
+pub enum PieceSetRef {
+    Builtin(String),
+    Inline(PieceSet),
+}
+
+impl PieceSetRef {
+    pub fn resolve(&self) -> Result<PieceSet, String> {
+        match self {
+            PieceSetRef::Builtin(name) => PieceSet::builtin(name),
+            PieceSetRef::Inline(set) => {
+                set.validate()?;
+                Ok(set.clone())
+            }
+        }
+    }
+}
+
+impl PieceSet {
+    pub fn builtin(name: &str) -> Result<Self, String> {
+        let json = BUILTIN_PIECE_SETS
+            .iter()
+            .find(|(builtin, _)| *builtin == name)
+            .map(|(_, json)| json)
+            .ok_or_else(|| format!("Unknown piece set: {}", name))?;
+        Ok(serde_json::from_str(json).expect("Broken built-in piece set."))
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.pieces.is_empty() {
+            return Err("A piece set needs at least one piece.".to_string());
+        }
+        for piece in self.pieces.iter() {
+            if !self.is_connected(&piece.cells) {
+                return Err(format!("The piece {} is not connected.", piece.name));
+            }
+        }
+        Ok(())
+    }
+
+    /// The `id`th piece with its orientations.
+    pub fn shape(&self, id: usize) -> Shape {
+        let cells: Vec<Point> = self.pieces[id]
+            .cells
+            .iter()
+            .map(|&(x, y)| Point::new(x, y))
+            .collect();
+        Shape::new(id, &cells, self.geometry)
+    }
+
+    /// Whether every cell can be reached from the first one through shared sides.
+    fn is_connected(&self, cells: &[(i32, i32)]) -> bool {
+        let first = match cells.first() {
+            Some(first) => *first,
+            None => return false,
+        };
+        let mut reached = vec![first];
+        let mut i = 0;
+        while i < reached.len() {
+            let (x, y) = reached[i];
+            for (dx, dy) in self.geometry.line_neighbors(Point::new(x, y)).iter() {
+                let next = (x + dx, y + dy);
+                if cells.contains(&next) && !reached.contains(&next) {
+                    reached.push(next);
+                }
+            }
+            i += 1;
+        }
+        cells.iter().all(|cell| reached.contains(cell))
+    }
+
+    /// Tray position of every piece, relative to the player.
+    pub fn layout(&self) -> Vec<(i32, i32)> {
+        let mut x = 0;
+        let mut y = self.fixed_tray_bottom().map_or(2, |bottom| bottom + 2);
+        let mut row_height = 0;
+        self.pieces
+            .iter()
+            .map(|piece| {
+                if let Some(tray) = piece.tray {
+                    return tray;
+                }
+                let min_x = piece.cells.iter().map(|c| c.0).min().unwrap();
+                let max_x = piece.cells.iter().map(|c| c.0).max().unwrap();
+                let min_y = piece.cells.iter().map(|c| c.1).min().unwrap();
+                let max_y = piece.cells.iter().map(|c| c.1).max().unwrap();
+                if x > 0 && x + max_x - min_x >= TRAY_WIDTH {
+                    x = 0;
+                    y += row_height + 1;
+                    row_height = 0;
+                }
+                let mut at = (x - min_x, y - min_y);
+                // Triangles keep pointing the same way only on even positions.
+                if self.geometry == Geometry::Triangle && (at.0 + at.1).rem_euclid(2) != 0 {
+                    at.0 += 1;
+                    x += 1;
+                }
+                x += max_x - min_x + 2;
+                row_height = row_height.max(max_y - min_y + 1);
+                at
+            })
+            .collect()
+    }
+
+    /// Last row used by the pieces with a fixed tray position.
+    fn fixed_tray_bottom(&self) -> Option<i32> {
+        self.pieces
+            .iter()
+            .filter_map(|piece| {
+                let (_, y) = piece.tray?;
+                piece.cells.iter().map(|c| y + c.1).max()
+            })
+            .max()
+    }
+}
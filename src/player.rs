@@ -1,8 +1,9 @@
 use super::{
-    GiveUpEvent, Input, Map, Mode, Player, Polynomio, Position, PutEvent, State, TurnChangeEvent,
+    GameEvent, Input, Map, Mode, Player, Polynomio, Position, Rules, Seating, State, UndoPolicy,
+    UndoRequest,
 };
 use crate::{ClientState, UserInput};
-use rltk::{Point, Rltk, VirtualKeyCode};
+use rltk::Point;
 use specs::Entity;
 use specs::WorldExt;
 
@@ -27,22 +28,22 @@ pub fn player_input(gs: &mut State, user_input: UserInput) -> InputResult {
         };
     }
 
-    if user_input.input == Input::Undo && mode == Mode::Select {
-        gs.undo();
-        return Updated {
-            newmode: mode,
-            trigger: Some(user_input),
-        };
+    if mode == Mode::Select {
+        match user_input.input {
+            Input::Undo => return request_undo(gs, user_input),
+            Input::ApproveUndo | Input::RejectUndo => return vote_undo(gs, user_input),
+            _ => {}
+        }
     }
 
-    let active_player_id = *gs.ecs.read_resource::<usize>() as i32;
+    let active_seat = gs.active_seat();
     let result = match (user_input.player_id, mode) {
         (_, Mode::Initialize) => Updated {
             newmode: Mode::Select,
             trigger: None,
         },
-        (pid, Mode::Select) if pid == active_player_id => player_input_select(gs, user_input),
-        (pid, Mode::Put) if pid == active_player_id => player_input_put(gs, user_input),
+        (pid, Mode::Select) if pid == active_seat => player_input_select(gs, user_input),
+        (pid, Mode::Put) if pid == active_seat => player_input_put(gs, user_input),
         (_, _) => Noop,
     };
 
@@ -61,6 +62,73 @@ pub fn player_input(gs: &mut State, user_input: UserInput) -> InputResult {
     result
 }
 
+fn request_undo(gs: &mut State, user_input: UserInput) -> InputResult {
+    let policy = gs.ecs.fetch::<Rules>().undo_policy();
+    let mover = match gs.last_mover() {
+        Some(mover) => mover,
+        None => return Noop,
+    };
+
+    // Everyone shares the keyboard in a local game, so only "disabled" applies.
+    let allowed = match policy {
+        UndoPolicy::Disabled => false,
+        _ if gs.use_local_input => true,
+        UndoPolicy::Anyone => true,
+        UndoPolicy::MoverOnly => mover == user_input.player_id,
+        UndoPolicy::Vote => {
+            if gs.ecs.fetch::<Option<UndoRequest>>().is_some() {
+                return Noop;
+            }
+            let voters = gs.undo_voters(user_input.player_id);
+            if voters.is_empty() {
+                true
+            } else {
+                *gs.ecs.fetch_mut::<Option<UndoRequest>>() =
+                    Some(UndoRequest::new(user_input.player_id, voters));
+                false
+            }
+        }
+    };
+
+    if allowed {
+        gs.undo();
+    } else if policy != UndoPolicy::Vote {
+        return Noop;
+    }
+
+    Updated {
+        newmode: Mode::Select,
+        trigger: Some(user_input),
+    }
+}
+
+fn vote_undo(gs: &mut State, user_input: UserInput) -> InputResult {
+    let approved = {
+        let mut undo_request = gs.ecs.fetch_mut::<Option<UndoRequest>>();
+        let request = match undo_request.as_mut() {
+            Some(request) if request.can_vote(user_input.player_id) => request,
+            _ => return Noop,
+        };
+
+        if user_input.input == Input::ApproveUndo {
+            request.approve(user_input.player_id);
+            request.is_approved()
+        } else {
+            *undo_request = None;
+            false
+        }
+    };
+    if approved {
+        *gs.ecs.fetch_mut::<Option<UndoRequest>>() = None;
+        gs.undo();
+    }
+
+    Updated {
+        newmode: Mode::Select,
+        trigger: Some(user_input),
+    }
+}
+
 fn player_input_select(gs: &mut State, user_input: UserInput) -> InputResult {
     let player_entity: Entity;
     let active_player_id: usize;
@@ -83,6 +151,9 @@ fn player_input_select(gs: &mut State, user_input: UserInput) -> InputResult {
             Input::Left => {
                 updated = select_next(player, true);
             }
+            Input::SelectPiece(i) => {
+                updated = select_piece(player, i);
+            }
             Input::Enter => {
                 active_position.x = player.cursor.x + map.x;
                 active_position.y = player.cursor.y + map.y;
@@ -99,12 +170,12 @@ fn player_input_select(gs: &mut State, user_input: UserInput) -> InputResult {
     }
     if ended {
         gs.next_player();
-        gs.push_event(Box::new(GiveUpEvent {
-            player_entity: player_entity,
-        }));
-        gs.push_event(Box::new(TurnChangeEvent {
+        gs.push_event(GameEvent::GiveUp {
+            player_id: active_player_id as i32,
+        });
+        gs.push_event(GameEvent::TurnChange {
             from: active_player_id,
-        }));
+        });
     }
 
     match updated {
@@ -123,6 +194,8 @@ fn player_input_put(gs: &mut State, user_input: UserInput) -> InputResult {
     let player_entity;
     let player_select;
     let player;
+    let mut placed_at = Point::new(0, 0);
+    let mut placed_orientation = 0;
     let mut updated = false;
     let mut newmode = Mode::Put;
     {
@@ -176,6 +249,8 @@ fn player_input_put(gs: &mut State, user_input: UserInput) -> InputResult {
                 if map.try_put(put_to, active_polynomio, active_player_id as i32) {
                     player.fixed[player.select] = true;
                     player.cursor = put_to;
+                    placed_at = put_to;
+                    placed_orientation = active_polynomio.orientation;
                     if !select_next(player, false) {
                         player.end = true;
                         ended = true;
@@ -196,17 +271,20 @@ fn player_input_put(gs: &mut State, user_input: UserInput) -> InputResult {
     }
     if next_player {
         gs.next_player();
-        gs.push_event(Box::new(PutEvent {
-            player_entity: player_entity,
+        gs.push_event(GameEvent::Put {
+            player_id: active_player_id as i32,
             polynomio_id: player_select,
-        }));
-        gs.push_event(Box::new(TurnChangeEvent {
+            x: placed_at.x,
+            y: placed_at.y,
+            orientation: placed_orientation,
+        });
+        gs.push_event(GameEvent::TurnChange {
             from: active_player_id,
-        }));
+        });
         if ended {
-            gs.push_event(Box::new(GiveUpEvent {
-                player_entity: player_entity,
-            }));
+            gs.push_event(GameEvent::GiveUp {
+                player_id: active_player_id as i32,
+            });
         }
     }
 
@@ -237,27 +315,15 @@ fn select_next(player: &mut Player, reverse: bool) -> bool {
     return false;
 }
 
-pub fn map_virtual_key_code(key: Option<VirtualKeyCode>) -> Option<Input> {
-    match key {
-        None => None,
-        Some(key) => match key {
-            VirtualKeyCode::R => Some(Input::RotateRight),
-            VirtualKeyCode::E => Some(Input::RotateLeft),
-            VirtualKeyCode::F => Some(Input::Flip),
-            VirtualKeyCode::Up => Some(Input::Up),
-            VirtualKeyCode::Down => Some(Input::Down),
-            VirtualKeyCode::Left => Some(Input::Left),
-            VirtualKeyCode::Right => Some(Input::Right),
-            VirtualKeyCode::Return => Some(Input::Enter),
-            VirtualKeyCode::Escape => Some(Input::Cancel),
-            VirtualKeyCode::Key0 => Some(Input::GiveUp),
-            VirtualKeyCode::Z => Some(Input::Undo),
-            _ => None,
-        },
+fn select_piece(player: &mut Player, i: usize) -> bool {
+    if i >= player.polynomios.len() || player.fixed[i] || i == player.select {
+        return false;
     }
+    player.select = i;
+    true
 }
 
-pub fn player_input_client(gs: &mut ClientState, ctx: &mut Rltk) -> Mode {
+pub fn player_input_client(gs: &mut ClientState, input: Option<Input>) -> Mode {
     let mode = *gs.ecs.fetch::<Mode>();
 
     if gs.locked {
@@ -265,12 +331,14 @@ pub fn player_input_client(gs: &mut ClientState, ctx: &mut Rltk) -> Mode {
     }
 
     let active_player_id = *gs.ecs.read_resource::<usize>();
-    if gs.player_id.unwrap() as usize != active_player_id {
+    let active_seat = gs
+        .ecs
+        .fetch::<Seating>()
+        .controller(active_player_id, &gs.ecs.fetch::<Vec<GameEvent>>());
+    if gs.player_id.unwrap() as usize != active_seat {
         return mode;
     }
 
-    let input = map_virtual_key_code(ctx.key);
-
     match input {
         Some(input) => match mode {
             Mode::Initialize => Mode::Select,
@@ -289,6 +357,9 @@ pub fn player_input_client(gs: &mut ClientState, ctx: &mut Rltk) -> Mode {
                     Input::Left => {
                         select_next(player, true);
                     }
+                    Input::SelectPiece(i) => {
+                        select_piece(player, i);
+                    }
                     Input::Enter => {
                         active_position.x = player.cursor.x + map.x;
                         active_position.y = player.cursor.y + map.y;
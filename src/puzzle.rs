@@ -0,0 +1,172 @@
+use super::{solve, Budget, Map, Player, Polynomio, Shape};
+use serde::{Deserialize, Serialize};
+use specs::prelude::*;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Where the best results of every puzzle are kept.
+const BEST_FILE: &str = "blokus-puzzle.json";
+
+/// How long the solver may think after every move.
+const CHECK_TIME: Duration = Duration::from_millis(100);
+
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum Solvability {
+    Solvable,
+    Unsolvable,
+    /// The solver ran out of time.
+    Unknown,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct PuzzleBest {
+    pub placed: usize,
+    pub time: f32,
+}
+
+impl PuzzleBest {
+    /// More cells placed is better, then a faster time.
+    pub fn is_better_than(&self, other: &PuzzleBest) -> bool {
+        self.placed > other.placed || (self.placed == other.placed && self.time < other.time)
+    }
+}
+
+/// A solo game where the goal is to place every piece.
+pub struct Puzzle {
+    pub key: String,
+    pub best: Option<PuzzleBest>,
+    pub new_best: bool,
+    pub solvable: Solvability,
+    /// Length of the history when `solvable` was computed.
+    checked_at: Option<usize>,
+    /// Length of the history the solver is thinking about, and where it writes
+    /// its answer.
+    pending: Option<(usize, Arc<Mutex<Option<Solvability>>>)>,
+}
+
+impl Puzzle {
+    pub fn new(key: &str) -> Self {
+        Puzzle {
+            key: key.to_string(),
+            best: load_bests().get(key).cloned(),
+            new_best: false,
+            solvable: Solvability::Unknown,
+            checked_at: None,
+            pending: None,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.new_best = false;
+        self.checked_at = None;
+        self.pending = None;
+    }
+
+    /// Keeps the result if it beats the best one, and saves it.
+    pub fn record(&mut self, result: PuzzleBest) {
+        if let Some(best) = &self.best {
+            if !result.is_better_than(best) {
+                return;
+            }
+        }
+        self.best = Some(result);
+        self.new_best = true;
+
+        let mut bests = load_bests();
+        bests.insert(self.key.clone(), result);
+        let serialized = serde_json::to_string_pretty(&bests).expect("Failed to serialize the bests.");
+        if let Err(err) = std::fs::write(BEST_FILE, serialized) {
+            eprintln!("[ERROR] Failed to save the best result to {}: {:?}", BEST_FILE, err);
+        }
+    }
+}
+
+fn load_bests() -> HashMap<String, PuzzleBest> {
+    std::fs::read_to_string(BEST_FILE)
+        .ok()
+        .and_then(|serialized| serde_json::from_str(&serialized).ok())
+        .unwrap_or_default()
+}
+
+/// Cells placed so far and the pieces still in the tray of the solo player.
+fn progress(ecs: &World) -> (PuzzleBest, Vec<(usize, Shape)>) {
+    let player_entity = ecs.fetch::<Vec<Entity>>()[0];
+    let players = ecs.read_storage::<Player>();
+    let polynomios = ecs.read_storage::<Polynomio>();
+    let player = players.get(player_entity).unwrap();
+
+    let mut placed = 0;
+    let mut pieces = Vec::new();
+    for (i, (e, fixed)) in player.polynomios.iter().zip(player.fixed.iter()).enumerate() {
+        let polynomio = polynomios.get(*e).unwrap();
+        if *fixed {
+            placed += polynomio.coods.len();
+        } else {
+            pieces.push((i, polynomio.shape.clone()));
+        }
+    }
+    let result = PuzzleBest {
+        placed,
+        time: player.time_used,
+    };
+    (result, pieces)
+}
+
+/// Asks the solver whether the remaining pieces can still all be placed, once
+/// per move. The solver thinks on its own thread so that the frames go on, and
+/// the answer is `Unknown` until it is done. Called every frame, returns whether
+/// the answer changed.
+pub fn check_puzzle(ecs: &World) -> bool {
+    let history_len = ecs.fetch::<Vec<crate::GameEvent>>().len();
+    let mut puzzle = ecs.fetch_mut::<Option<Puzzle>>();
+    let puzzle = match puzzle.as_mut() {
+        Some(puzzle) if puzzle.checked_at != Some(history_len) => puzzle,
+        _ => return false,
+    };
+
+    if let Some((checking, answer)) = &puzzle.pending {
+        if *checking == history_len {
+            let solvable = match *answer.lock().unwrap() {
+                Some(solvable) => solvable,
+                None => return false,
+            };
+            puzzle.checked_at = Some(history_len);
+            puzzle.pending = None;
+            let changed = puzzle.solvable != solvable;
+            puzzle.solvable = solvable;
+            return changed;
+        }
+    }
+
+    // A move was made or undone: the previous answer, if any, is out of date.
+    let (_, pieces) = progress(ecs);
+    let map = (*ecs.fetch::<Map>()).clone();
+    let answer = Arc::new(Mutex::new(None));
+    let solver_answer = answer.clone();
+    std::thread::spawn(move || {
+        let budget = Budget {
+            nodes: None,
+            time: Some(CHECK_TIME),
+        };
+        let solution = solve(&map, 0, &pieces, budget);
+        *solver_answer.lock().unwrap() = Some(if solution.places_all() {
+            Solvability::Solvable
+        } else if solution.exhaustive {
+            Solvability::Unsolvable
+        } else {
+            Solvability::Unknown
+        });
+    });
+    puzzle.pending = Some((history_len, answer));
+    let changed = puzzle.solvable != Solvability::Unknown;
+    puzzle.solvable = Solvability::Unknown;
+    changed
+}
+
+pub fn finish_puzzle(ecs: &World) {
+    let (result, _) = progress(ecs);
+    if let Some(puzzle) = ecs.fetch_mut::<Option<Puzzle>>().as_mut() {
+        puzzle.record(result);
+    }
+}
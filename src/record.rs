@@ -0,0 +1,87 @@
+use crate::{dump_game, GameEvent, Player, Rules, SeriesFile};
+use serde::{Deserialize, Serialize};
+use specs::prelude::*;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PlayerRecord {
+    pub id: i32,
+    pub name: String,
+    pub rank: i32,
+    pub score: i32,
+    pub remaining_tiles: i32,
+    pub placed: usize,
+    pub time_used: f32,
+}
+
+/// A game, finished or saved while playing: the summary shown on the results
+/// screen, the move list, and the full serialized game so the position can be
+/// loaded again.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct GameRecord {
+    pub rules: Rules,
+    pub players: Vec<PlayerRecord>,
+    pub history: Vec<GameEvent>,
+    pub game: String,
+}
+
+impl GameRecord {
+    pub fn new(ecs: &mut World) -> Self {
+        let players = {
+            let player_entities = ecs.fetch::<Vec<Entity>>();
+            let players = ecs.read_storage::<Player>();
+            player_entities
+                .iter()
+                .map(|e| {
+                    let player = players.get(*e).unwrap();
+                    PlayerRecord {
+                        id: player.id,
+                        name: player.display_name(),
+                        rank: player.rank,
+                        score: player.score,
+                        remaining_tiles: player.remaining_tiles,
+                        placed: player.placed(),
+                        time_used: player.time_used,
+                    }
+                })
+                .collect()
+        };
+
+        let rules = (*ecs.fetch::<Rules>()).clone();
+        let history = (*ecs.fetch::<Vec<GameEvent>>()).clone();
+        GameRecord {
+            rules,
+            players,
+            history,
+            game: dump_game(ecs),
+        }
+    }
+}
+
+/// Writes the record of the current game into the working directory and returns the file name.
+pub fn save_record(ecs: &mut World) -> std::io::Result<String> {
+    let record = GameRecord::new(ecs);
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let path = format!("blokus-{}.json", timestamp);
+    let serialized = serde_json::to_string_pretty(&record).expect("Failed to serialize the record.");
+    std::fs::write(&path, serialized)?;
+    Ok(path)
+}
+
+/// Reads the serialized game of a saved game record, or of the game in progress of a series.
+pub fn load_position(path: &str) -> std::io::Result<String> {
+    let serialized = std::fs::read_to_string(path)?;
+    if let Ok(record) = serde_json::from_str::<GameRecord>(&serialized) {
+        return Ok(record.game);
+    }
+    match SeriesFile::load(path)?.game {
+        Some(game) => Ok(game),
+        None => Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "The series has no game in progress.",
+        )),
+    }
+}
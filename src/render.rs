@@ -1,47 +1,99 @@
 use crate::Mode;
 use crate::PlayerSlotManager;
+use crate::{
+    analyze, heatmap, Analysis, GameEvent, Input, KeyBindings, Layout, Owner, Puzzle, PuzzleBest,
+    Screen, Seating, Series, Solvability, UndoRequest, HISTORY_WIDTH,
+};
 use actix_web::web::Data;
 use specs::prelude::*;
 use std::sync::Mutex;
 
-use super::{Map, Player, Polynomio, Position, Rect, EMPTY, WALL};
-use rltk::{Rltk, RGB};
+use super::{Geometry, Map, Player, Polynomio, Position, Rect, EMPTY, WALL};
+use rltk::RGB;
 
-pub fn render(ecs: &World, ctx: &mut Rltk, slot_manager: Option<Data<Mutex<PlayerSlotManager>>>) {
+pub fn render(
+    ecs: &World,
+    ctx: &mut impl Screen,
+    slot_manager: Option<Data<Mutex<PlayerSlotManager>>>,
+) {
     ctx.cls();
 
     let mode = *ecs.fetch::<Mode>();
 
+    if mode == Mode::Finish {
+        draw_results(ecs, ctx);
+        return;
+    }
+
+    let help_y = ecs.fetch::<Layout>().help_y;
+    let keys = ecs.fetch::<KeyBindings>();
     let dialogs = match mode {
         Mode::Initialize => vec![],
         Mode::Select => {
-            vec!["Left/Right: Select a piece to put  Enter: Put  Num0: Give up".to_string()]
+            let mut dialogs = vec![format!(
+                "{}/{}: Select a piece to put  {}: Put  {}: Give up",
+                keys.label(Input::Left),
+                keys.label(Input::Right),
+                keys.label(Input::Enter),
+                keys.label(Input::GiveUp)
+            )];
+            // A pending undo request takes the place of the second line.
+            if let Some(request) = &*ecs.fetch::<Option<UndoRequest>>() {
+                let players = ecs.read_storage::<Player>();
+                let requester = players
+                    .join()
+                    .find(|player| player.id == request.requester)
+                    .map(|player| player.display_name())
+                    .unwrap_or_else(|| format!("Player #{}", request.requester + 1));
+                dialogs.push(format!(
+                    "{} requests an undo ({}/{})  {}: Approve  {}: Reject",
+                    requester,
+                    request.approvals.len(),
+                    request.voters.len(),
+                    keys.label(Input::ApproveUndo),
+                    keys.label(Input::RejectUndo)
+                ));
+            } else {
+                dialogs.push(format!(
+                    "Shift+letter: Select by name  {}: Playable pieces only  {}: Save",
+                    keys.label(Input::TogglePlayable),
+                    keys.label(Input::SaveRecord)
+                ));
+            }
+            dialogs
         }
         Mode::Put => vec![
-            "Left/Right/Up/Down: Move a piece  Enter: Put  Num0: Give up".to_string(),
-            "R: Rotate right  E: Rotate left  F: Flip  Esc: Cancel".to_string(),
+            format!(
+                "{}/{}/{}/{}: Move a piece  {}: Put  {}: Give up",
+                keys.label(Input::Left),
+                keys.label(Input::Right),
+                keys.label(Input::Up),
+                keys.label(Input::Down),
+                keys.label(Input::Enter),
+                keys.label(Input::GiveUp)
+            ),
+            format!(
+                "{}: Rotate right  {}: Rotate left  {}: Flip  {}: Cancel  {}: Anchors",
+                keys.label(Input::RotateRight),
+                keys.label(Input::RotateLeft),
+                keys.label(Input::Flip),
+                keys.label(Input::Cancel),
+                keys.label(Input::ToggleAnchors)
+            ),
         ],
-        Mode::Finish => {
-            let entities = ecs.entities();
-            let players = ecs.read_storage::<Player>();
-            let winner = (&entities, &players)
-                .join()
-                .filter(|(_, player)| player.rank == 1)
-                .next()
-                .unwrap()
-                .1;
-            match &winner.name {
-                Some(name) => vec![format!["{} won!", name]],
-                None => vec![format!["Player #{} won!", winner.id + 1]],
-            }
-        }
+        Mode::Finish => vec![],
     };
     for (i, dialog) in dialogs.iter().enumerate() {
-        ctx.print(5, i * 2 + 60, dialog);
+        ctx.print(5, i as i32 * 2 + help_y, dialog);
+    }
+    if let Some(Notice(notice)) = &*ecs.fetch::<Option<Notice>>() {
+        ctx.print(5, help_y + 4, notice);
     }
 
     draw_map(ecs, ctx);
     draw_uis(&ecs, ctx, slot_manager);
+    draw_history(ecs, ctx);
+    draw_puzzle(ecs, ctx);
 
     draw_polynomios(&ecs, ctx, mode, true);
     draw_polynomios(&ecs, ctx, mode, false);
@@ -82,7 +134,259 @@ pub fn render(ecs: &World, ctx: &mut Rltk, slot_manager: Option<Data<Mutex<Playe
     }
 }
 
-pub fn draw_map(ecs: &World, ctx: &mut Rltk) {
+pub struct Notice(pub String);
+
+/// Hints drawn over the board. Each window toggles them for itself, so they are not synced.
+#[derive(Default, Clone, Copy)]
+pub struct Overlays {
+    /// Cells where the player to move can start a piece.
+    pub anchors: bool,
+    /// Moves, corners, territory and evaluation of every colour, under its stats.
+    pub analysis: bool,
+    /// Colours empty cells by who can reach them within this many moves. 0 is off.
+    pub heatmap: usize,
+    /// Leaves only the pieces with a legal placement in the trays.
+    pub playable: bool,
+}
+
+/// Deepest heatmap before it turns off again.
+const HEATMAP_MAX_DEPTH: usize = 3;
+
+impl Overlays {
+    pub fn cycle_heatmap(&mut self) {
+        self.heatmap = (self.heatmap + 1) % (HEATMAP_MAX_DEPTH + 1);
+    }
+}
+
+fn format_best(best: &PuzzleBest) -> String {
+    let time = best.time as i32;
+    format!("{} placed in {}:{:02}", best.placed, time / 60, time % 60)
+}
+
+/// Timer, best result and whether the remaining pieces can still all be placed.
+pub fn draw_puzzle(ecs: &World, ctx: &mut impl Screen) {
+    let puzzle = ecs.fetch::<Option<Puzzle>>();
+    let puzzle = match &*puzzle {
+        Some(puzzle) => puzzle,
+        None => return,
+    };
+    let map = ecs.fetch::<Map>();
+    let player_entities = ecs.fetch::<Vec<Entity>>();
+    let players = ecs.read_storage::<Player>();
+    let player = match player_entities.first().and_then(|e| players.get(*e)) {
+        Some(player) => player,
+        None => return,
+    };
+
+    let time = player.time_used as i32;
+    let solvable = match puzzle.solvable {
+        Solvability::Solvable => "yes",
+        Solvability::Unsolvable => "no",
+        Solvability::Unknown => "unknown",
+    };
+    let y = map.y + map.height as i32 + 1;
+    ctx.print(map.x, y, format!("Time: {}:{:02}", time / 60, time % 60));
+    ctx.print(map.x, y + 1, format!("Solvable: {}", solvable));
+    if let Some(best) = &puzzle.best {
+        ctx.print(map.x, y + 2, format!("Best: {}", format_best(best)));
+    }
+}
+
+pub fn draw_results(ecs: &World, ctx: &mut impl Screen) {
+    let player_entities = ecs.fetch::<Vec<Entity>>();
+    let players = ecs.read_storage::<Player>();
+    let polynomios = ecs.read_storage::<Polynomio>();
+
+    let mut ranking: Vec<&Player> = player_entities
+        .iter()
+        .map(|e| players.get(*e).unwrap())
+        .collect();
+    ranking.sort_by_key(|player| (player.rank == 0, player.rank, player.id));
+
+    ctx.print(5, 2, "Results");
+    // A seat playing several colours is announced once.
+    let seating = ecs.fetch::<Seating>();
+    let mut winner_seats: Vec<Owner> = Vec::new();
+    let names: Vec<String> = ranking
+        .iter()
+        .filter(|player| player.rank == 1)
+        .filter(|player| {
+            let seat = seating.owner(player.id as usize);
+            let first = !winner_seats.contains(&seat);
+            winner_seats.push(seat);
+            first
+        })
+        .map(|player| player.display_name())
+        .collect();
+    let announcement = match names.len() {
+        0 => String::new(),
+        1 => format!["{} won!", names[0]],
+        n => format![
+            "{} and {} tied for the win!",
+            names[..n - 1].join(", "),
+            names[n - 1]
+        ],
+    };
+    ctx.print(5, 4, announcement);
+    if let Some(puzzle) = &*ecs.fetch::<Option<Puzzle>>() {
+        let best = match &puzzle.best {
+            Some(best) if puzzle.new_best => format!("New best! {}", format_best(best)),
+            Some(best) => format!("Best: {}", format_best(best)),
+            None => String::new(),
+        };
+        ctx.print(5, 5, best);
+    }
+
+    // Leftover pieces wrap to a new row at the right edge of the console.
+    let right = match ecs.fetch::<Layout>().screen.0 {
+        0 => 72,
+        width => width,
+    };
+    let mut y = 7;
+    for player in ranking {
+        let rank = rank_label(player);
+        let time = player.time_used as i32;
+        let line = format![
+            "{:<4}{:<22}score: {:>4}  placed: {:>2}/{:<2}  time: {}:{:02}",
+            rank,
+            player.display_name(),
+            player.score,
+            player.placed(),
+            player.fixed.len(),
+            time / 60,
+            time % 60
+        ];
+        ctx.print_color(5, y, player.color, RGB::named(rltk::BLACK), &line);
+
+        let (mut x, mut row_y, mut row_height) = (9, y + 2, 0);
+        for (&polynomio_entity, &fixed) in player.polynomios.iter().zip(player.fixed.iter()) {
+            if fixed {
+                continue;
+            }
+            let polynomio = polynomios.get(polynomio_entity).unwrap();
+            let upper_left = polynomio.upper_left();
+            let width = polynomio
+                .coods
+                .iter()
+                .map(|c| c.x - upper_left.x + 1)
+                .max()
+                .unwrap_or(0);
+            let height = polynomio
+                .coods
+                .iter()
+                .map(|c| c.y - upper_left.y + 1)
+                .max()
+                .unwrap_or(0);
+            if x > 9 && x + width > right {
+                x = 9;
+                row_y += row_height + 1;
+                row_height = 0;
+            }
+            for cood in &polynomio.coods {
+                let p = *cood - upper_left;
+                ctx.set(
+                    x + p.x,
+                    row_y + p.y,
+                    player.color,
+                    cell_background(polynomio.geometry(), player.color * 0.8),
+                    polynomio.geometry().glyph(*cood),
+                );
+            }
+            x += width + 1;
+            row_height = row_height.max(height);
+        }
+        y = row_y + row_height + 2;
+    }
+
+    if let Some(series) = &*ecs.fetch::<Option<Series>>() {
+        // Series scores are kept per seat; a seat is shown with its first colour.
+        let seat_entities: Vec<Entity> = (0..seating.seats)
+            .filter_map(|seat| seating.colors_of(seat).first().map(|&c| player_entities[c]))
+            .collect();
+        y = y.max(44);
+        draw_series(ctx, series, &seat_entities, &players, y);
+        y += seat_entities.len() as i32 + 3;
+    }
+    let y = y.max(60);
+
+    let rematch = match &*ecs.fetch::<Option<Series>>() {
+        Some(series) if series.is_over() => "New series",
+        Some(_) => "Next game",
+        None => "Rematch",
+    };
+    let keys = ecs.fetch::<KeyBindings>();
+    ctx.print(
+        5,
+        y,
+        format!(
+            "{}: {}  {}: Save the game record",
+            keys.label(Input::Enter),
+            rematch,
+            keys.label(Input::SaveRecord)
+        ),
+    );
+    if let Some(Notice(notice)) = &*ecs.fetch::<Option<Notice>>() {
+        ctx.print(5, y + 2, notice);
+    }
+}
+
+fn rank_label(player: &Player) -> String {
+    match player.rank {
+        0 => "-".to_string(),
+        rank => format!["#{}{}", rank, if player.tied { "=" } else { "" }],
+    }
+}
+
+pub fn draw_series(
+    ctx: &mut impl Screen,
+    series: &Series,
+    player_entities: &[Entity],
+    players: &ReadStorage<Player>,
+    y: i32,
+) {
+    let title = if series.is_over() {
+        format!("Series final standings ({} games)", series.games)
+    } else {
+        format!(
+            "Series standings after game {} of {}",
+            series.played(),
+            series.games
+        )
+    };
+    ctx.print(5, y, title);
+
+    let totals = series.totals();
+    let ranks = series.ranks();
+    let mut order: Vec<usize> = (0..totals.len()).collect();
+    order.sort_by_key(|&i| (ranks[i], i));
+    for (line, i) in order.into_iter().enumerate() {
+        let player = match player_entities.get(i).and_then(|e| players.get(*e)) {
+            Some(player) => player,
+            None => continue,
+        };
+        let games: Vec<String> = series
+            .results
+            .iter()
+            .map(|r| r.get(i).cloned().unwrap_or(0).to_string())
+            .collect();
+        let text = format!(
+            "#{:<3}{:<22}total: {:>4}  ({})",
+            ranks[i],
+            player.display_name(),
+            totals[i],
+            games.join(", ")
+        );
+        ctx.print_color(
+            5,
+            y + 2 + line as i32,
+            player.color,
+            RGB::named(rltk::BLACK),
+            text,
+        );
+    }
+}
+
+pub fn draw_map(ecs: &World, ctx: &mut impl Screen) {
     let map = ecs.read_resource::<Map>();
 
     for (idx, &tile) in map.map.iter().enumerate() {
@@ -92,8 +396,8 @@ pub fn draw_map(ecs: &World, ctx: &mut Rltk) {
                 p.x + map.x,
                 p.y + map.y,
                 RGB::named(rltk::WHITE) * 0.8,
-                RGB::named(rltk::WHITE) * 0.9,
-                rltk::to_cp437('■'),
+                cell_background(map.geometry, RGB::named(rltk::WHITE) * 0.9),
+                map.geometry.glyph(p),
             );
         } else if tile == WALL {
             ctx.set(
@@ -111,17 +415,149 @@ pub fn draw_map(ecs: &World, ctx: &mut Rltk) {
             map.starts[key].x + map.x,
             map.starts[key].y + map.y,
             map.colors[key],
-            RGB::named(rltk::WHITE) * 0.9,
-            rltk::to_cp437('■'),
+            cell_background(map.geometry, RGB::named(rltk::WHITE) * 0.9),
+            map.geometry.glyph(map.starts[key]),
+        );
+    }
+
+    let overlays = *ecs.fetch::<Overlays>();
+    if overlays.heatmap > 0 {
+        draw_heatmap(ecs, ctx, &map, overlays.heatmap);
+    }
+    if overlays.anchors {
+        draw_anchors(ecs, ctx, &map);
+    }
+}
+
+/// Tints every empty cell with the colours that can reach it, mixed when several can.
+fn draw_heatmap(ecs: &World, ctx: &mut impl Screen, map: &Map, depth: usize) {
+    let reach = heatmap(ecs, depth);
+    for idx in (0..map.map.len()).filter(|&idx| map.map[idx] == EMPTY) {
+        let colors: Vec<RGB> = reach
+            .iter()
+            .filter(|(_, cells)| cells.get(idx))
+            .filter_map(|(player_id, _)| map.colors.get(player_id))
+            .cloned()
+            .collect();
+        if colors.is_empty() {
+            continue;
+        }
+        let n = colors.len() as f32;
+        let mix = |channel: fn(&RGB) -> f32| {
+            let average = colors.iter().map(channel).sum::<f32>() / n;
+            0.5 + 0.5 * average
+        };
+        let tint = RGB::from_f32(mix(|c| c.r), mix(|c| c.g), mix(|c| c.b));
+        let p = map.idx_point(idx);
+        ctx.set(
+            p.x + map.x,
+            p.y + map.y,
+            tint * 0.8,
+            cell_background(map.geometry, tint * 0.9),
+            map.geometry.glyph(p),
+        );
+    }
+
+    let moves = if depth == 1 { "move" } else { "moves" };
+    ctx.print(
+        map.x,
+        map.y + map.height as i32,
+        format!(
+            "Heatmap: reach within {} {}  {}: Change",
+            depth,
+            moves,
+            ecs.fetch::<KeyBindings>().label(Input::CycleHeatmap)
+        ),
+    );
+}
+
+fn draw_anchors(ecs: &World, ctx: &mut impl Screen, map: &Map) {
+    let active_player_id = *ecs.fetch::<usize>() as i32;
+    let color = match map.colors.get(&active_player_id) {
+        Some(color) => *color,
+        None => return,
+    };
+    for p in map.anchors(active_player_id) {
+        let glyph = match map.geometry {
+            Geometry::Square => rltk::to_cp437('∙'),
+            Geometry::Triangle => map.geometry.glyph(p),
+        };
+        ctx.set(
+            p.x + map.x,
+            p.y + map.y,
+            color * 0.7,
+            cell_background(map.geometry, RGB::named(rltk::WHITE) * 0.9),
+            glyph,
+        );
+    }
+}
+
+/// Triangles are drawn as glyphs on a dark background so that their shape stays visible.
+fn cell_background(geometry: Geometry, color: RGB) -> RGB {
+    match geometry {
+        Geometry::Square => color,
+        Geometry::Triangle => RGB::named(rltk::BLACK),
+    }
+}
+
+pub fn draw_history(ecs: &World, ctx: &mut impl Screen) {
+    let map = ecs.read_resource::<Map>();
+    let history = ecs.fetch::<Vec<GameEvent>>();
+    let player_entities = ecs.fetch::<Vec<Entity>>();
+    let players = ecs.read_storage::<Player>();
+    let polynomios = ecs.read_storage::<Polynomio>();
+
+    let moves: Vec<(usize, &GameEvent)> = history
+        .iter()
+        .filter(|event| event.is_move())
+        .enumerate()
+        .collect();
+    let lines = map.height.saturating_sub(1);
+    let skip = moves.len().saturating_sub(lines);
+
+    let x = map.x - HISTORY_WIDTH;
+    ctx.print(x, map.y, "Moves");
+    for (line, (i, event)) in moves.iter().skip(skip).enumerate() {
+        let text = match event {
+            GameEvent::Put {
+                player_id,
+                polynomio_id,
+                x,
+                y,
+                ..
+            } => {
+                let name = player_entities
+                    .get(*player_id as usize)
+                    .and_then(|e| players.get(*e))
+                    .and_then(|player| polynomios.get(player.polynomios[*polynomio_id]))
+                    .map(|polynomio| polynomio.name.clone())
+                    .unwrap_or_else(|| format!("piece {}", polynomio_id + 1));
+                format!("{:>3}. {} at ({},{})", i + 1, name, x, y)
+            }
+            GameEvent::GiveUp { .. } => format!("{:>3}. gave up", i + 1),
+            GameEvent::TurnChange { .. } => continue,
+        };
+        let color = map
+            .colors
+            .get(&event.player_id())
+            .cloned()
+            .unwrap_or_else(|| RGB::named(rltk::WHITE));
+        ctx.print_color(
+            x,
+            map.y + 1 + line as i32,
+            color,
+            RGB::named(rltk::BLACK),
+            &text,
         );
     }
 }
 
-pub fn draw_polynomios(ecs: &World, ctx: &mut Rltk, mode: Mode, bg: bool) {
+pub fn draw_polynomios(ecs: &World, ctx: &mut impl Screen, mode: Mode, bg: bool) {
     let positions = ecs.read_storage::<Position>();
     let polynomios = ecs.read_storage::<Polynomio>();
-    for (pos, polynomio) in (&positions, &polynomios).join() {
-        if polynomio.bg != bg {
+    let hidden = unplayable_pieces(ecs);
+    for (e, pos, polynomio) in (&ecs.entities(), &positions, &polynomios).join() {
+        if polynomio.bg != bg || hidden.contains(&e) {
             continue;
         }
         let alpha = if mode == Mode::Put && polynomio.fixed {
@@ -133,20 +569,46 @@ pub fn draw_polynomios(ecs: &World, ctx: &mut Rltk, mode: Mode, bg: bool) {
     }
 }
 
-pub fn draw_polynomio(ctx: &mut Rltk, pos: &Position, polynomio: &Polynomio, alpha: f32) {
+/// Pieces left in the trays without a legal placement, while only playable
+/// pieces are shown. Their outline stays in the tray.
+pub fn unplayable_pieces(ecs: &World) -> Vec<Entity> {
+    if !ecs.fetch::<Overlays>().playable || *ecs.fetch::<Mode>() == Mode::Finish {
+        return Vec::new();
+    }
+    let analyses = analyze(ecs);
+    let players = ecs.read_storage::<Player>();
+    let mut hidden = Vec::new();
+    for player in players.join() {
+        let pieces = match analyses
+            .iter()
+            .find(|analysis| analysis.player_id == player.id)
+        {
+            Some(analysis) => &analysis.pieces,
+            None => continue,
+        };
+        for (i, e) in player.polynomios.iter().enumerate() {
+            if !player.fixed[i] && !pieces.contains(&i) {
+                hidden.push(*e);
+            }
+        }
+    }
+    hidden
+}
+
+pub fn draw_polynomio(ctx: &mut impl Screen, pos: &Position, polynomio: &Polynomio, alpha: f32) {
     let color = polynomio.color * alpha;
     for cood in &polynomio.coods {
         ctx.set(
             pos.x + cood.x,
             pos.y + cood.y,
             color,
-            color * 0.8,
-            rltk::to_cp437('■'),
+            cell_background(polynomio.geometry(), color * 0.8),
+            polynomio.geometry().glyph(*cood),
         );
     }
 }
 
-pub fn draw_rect(ctx: &mut Rltk, position: &Position, rect: &Rect) {
+pub fn draw_rect(ctx: &mut impl Screen, position: &Position, rect: &Rect) {
     for xi in position.x..position.x + rect.w {
         ctx.set(
             xi,
@@ -181,21 +643,66 @@ pub fn draw_rect(ctx: &mut Rltk, position: &Position, rect: &Rect) {
     }
 }
 
-pub fn draw_uis(ecs: &World, ctx: &mut Rltk, slot_manager: Option<Data<Mutex<PlayerSlotManager>>>) {
+pub fn draw_uis(
+    ecs: &World,
+    ctx: &mut impl Screen,
+    slot_manager: Option<Data<Mutex<PlayerSlotManager>>>,
+) {
     let positions = ecs.read_storage::<Position>();
     let players = ecs.read_storage::<Player>();
     let active_player_id = ecs.fetch::<usize>();
+    let seating = ecs.fetch::<Seating>();
+
+    // A shared colour shows who plays it this turn.
+    let controller = seating
+        .colors_of(seating.controller(*active_player_id, &ecs.fetch::<Vec<GameEvent>>()))
+        .first()
+        .and_then(|&color| players.join().find(|player| player.id as usize == color))
+        .filter(|player| player.id as usize != *active_player_id)
+        .map(|player| player.display_name());
 
     for (pos, player) in (&positions, &players).join() {
-        draw_ui(ctx, pos, player, *active_player_id, slot_manager.clone());
+        draw_ui(
+            ctx,
+            pos,
+            player,
+            *active_player_id,
+            controller.clone(),
+            slot_manager.clone(),
+        );
+    }
+
+    if ecs.fetch::<Overlays>().analysis {
+        let analyses = analyze(ecs);
+        for (pos, player) in (&positions, &players).join() {
+            if let Some(analysis) = analyses.iter().find(|a| a.player_id == player.id) {
+                draw_analysis(ctx, pos, player, analysis);
+            }
+        }
     }
 }
 
+/// One line under the stats of the player.
+fn draw_analysis(ctx: &mut impl Screen, position: &Position, player: &Player, analysis: &Analysis) {
+    let line = format![
+        "moves: {}  corners: {}  territory: {}  eval: {:+.1}",
+        analysis.moves, analysis.corners, analysis.territory, analysis.eval
+    ];
+    ctx.print_color(
+        position.x,
+        position.y + 1,
+        player.color * 0.7,
+        RGB::named(rltk::BLACK),
+        format!["{:>63}", line],
+    );
+}
+
 pub fn draw_ui(
-    ctx: &mut Rltk,
+    ctx: &mut impl Screen,
     position: &Position,
     player: &Player,
     active_player_id: usize,
+    controller: Option<String>,
     slot_manager: Option<Data<Mutex<PlayerSlotManager>>>,
 ) {
     let player_name = if let Some(slot_manager) = slot_manager {
@@ -219,12 +726,20 @@ pub fn draw_ui(
     let player_str = if player.end {
         format!["{} (Finished)", player_name]
     } else if player.id as usize == active_player_id {
-        format!["{} <= Your turn", player_name]
+        match controller {
+            Some(controller) => format!["{} <= {} plays", player_name, controller],
+            None => format!["{} <= Your turn", player_name],
+        }
     } else {
         format!["{}", player_name]
     };
 
-    let stats = &format!["remaining: {} (#{})", player.remaining_tiles, player.rank];
+    let stats = &format![
+        "remaining: {}  score: {} ({})",
+        player.remaining_tiles,
+        player.score,
+        rank_label(player)
+    ];
     let dialog = &format!["{:<30}{:>33}", player_str, stats];
 
     ctx.print_color(
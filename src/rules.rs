@@ -0,0 +1,149 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(PartialEq, Copy, Clone, Serialize, Deserialize, Debug)]
+pub enum UndoPolicy {
+    Anyone,
+    MoverOnly,
+    Vote,
+    Disabled,
+}
+
+impl UndoPolicy {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "anyone" => Some(UndoPolicy::Anyone),
+            "mover" => Some(UndoPolicy::MoverOnly),
+            "vote" => Some(UndoPolicy::Vote),
+            "disabled" => Some(UndoPolicy::Disabled),
+            _ => None,
+        }
+    }
+}
+
+#[derive(PartialEq, Copy, Clone, Serialize, Deserialize, Debug)]
+pub enum ScoringMode {
+    /// -1 per remaining square.
+    Simple,
+    /// Simple, plus 15 for placing all pieces.
+    Standard,
+    /// Standard, plus 5 more if the monomino was placed last.
+    Advanced,
+}
+
+impl ScoringMode {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "simple" => Some(ScoringMode::Simple),
+            "standard" => Some(ScoringMode::Standard),
+            "advanced" => Some(ScoringMode::Advanced),
+            _ => None,
+        }
+    }
+
+    pub fn score(&self, remaining_tiles: i32, monomino_last: bool) -> i32 {
+        let all_placed = remaining_tiles == 0;
+        match self {
+            ScoringMode::Simple => -remaining_tiles,
+            ScoringMode::Standard if all_placed => 15,
+            ScoringMode::Advanced if all_placed && monomino_last => 20,
+            ScoringMode::Advanced if all_placed => 15,
+            _ => -remaining_tiles,
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct Rules {
+    pub undo_policy: UndoPolicy,
+    pub ranked: bool,
+    pub scoring: ScoringMode,
+    pub rotate_start: bool,
+}
+
+impl Rules {
+    pub fn new(
+        undo_policy: UndoPolicy,
+        ranked: bool,
+        scoring: ScoringMode,
+        rotate_start: bool,
+    ) -> Self {
+        Rules {
+            undo_policy,
+            ranked,
+            scoring,
+            rotate_start,
+        }
+    }
+
+    /// Ranked games never allow undo, whatever policy was requested.
+    pub fn undo_policy(&self) -> UndoPolicy {
+        if self.ranked {
+            UndoPolicy::Disabled
+        } else {
+            self.undo_policy
+        }
+    }
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Rules::new(UndoPolicy::Anyone, false, ScoringMode::Simple, false)
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct UndoRequest {
+    pub requester: i32,
+    pub voters: Vec<i32>,
+    pub approvals: Vec<i32>,
+}
+
+impl UndoRequest {
+    pub fn new(requester: i32, voters: Vec<i32>) -> Self {
+        UndoRequest {
+            requester,
+            voters,
+            approvals: Vec::new(),
+        }
+    }
+
+    pub fn can_vote(&self, player_id: i32) -> bool {
+        self.voters.contains(&player_id) && !self.approvals.contains(&player_id)
+    }
+
+    pub fn approve(&mut self, player_id: i32) {
+        if self.can_vote(player_id) {
+            self.approvals.push(player_id);
+        }
+    }
+
+    pub fn is_approved(&self) -> bool {
+        self.voters.iter().all(|v| self.approvals.contains(v))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn simple_counts_the_remaining_squares_only() {
+        assert_eq!(ScoringMode::Simple.score(7, false), -7);
+        assert_eq!(ScoringMode::Simple.score(0, true), 0);
+    }
+
+    #[test]
+    fn standard_gives_15_for_placing_every_piece() {
+        assert_eq!(ScoringMode::Standard.score(0, false), 15);
+        assert_eq!(ScoringMode::Standard.score(0, true), 15);
+        assert_eq!(ScoringMode::Standard.score(4, false), -4);
+    }
+
+    #[test]
+    fn advanced_gives_5_more_only_when_the_monomino_goes_last() {
+        assert_eq!(ScoringMode::Advanced.score(0, true), 20);
+        assert_eq!(ScoringMode::Advanced.score(0, false), 15);
+        // The monomino going last does not count while pieces remain.
+        assert_eq!(ScoringMode::Advanced.score(5, true), -5);
+    }
+}
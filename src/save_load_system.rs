@@ -2,7 +2,9 @@ use crate::Map;
 use crate::Mode;
 use crate::SerializeHelper;
 use crate::SyncOnline;
+use crate::{GameEvent, Geometry, PieceSet, Rules, Seating, Series, Shape, UndoRequest};
 use crate::{Player, Polynomio, Position};
+use serde::de::Error;
 use specs::error::NoError;
 use specs::prelude::*;
 use specs::saveload::MarkedBuilder;
@@ -26,12 +28,18 @@ macro_rules! serialize_individually {
 
 pub fn dump_game(ecs: &mut World) -> String {
     // Create helper
-    let (mapcopy, active_player_id, mode) = {
+    let (mapcopy, active_player_id, mode, rules, undo_request, history, series, seating, piece_set) = {
         // let players = (*ecs.fetch_mut::<Vec<Entity>>()).clone();
         (
             (*ecs.fetch_mut::<Map>()).clone(),
             *ecs.fetch_mut::<usize>(),
             *ecs.fetch_mut::<Mode>(),
+            (*ecs.fetch::<Rules>()).clone(),
+            (*ecs.fetch::<Option<UndoRequest>>()).clone(),
+            (*ecs.fetch::<Vec<GameEvent>>()).clone(),
+            (*ecs.fetch::<Option<Series>>()).clone(),
+            (*ecs.fetch::<Seating>()).clone(),
+            (*ecs.fetch::<PieceSet>()).clone(),
         )
     };
 
@@ -41,6 +49,12 @@ pub fn dump_game(ecs: &mut World) -> String {
             map: mapcopy,
             active_player_id: active_player_id,
             mode: mode,
+            rules,
+            undo_request,
+            history,
+            series,
+            seating,
+            piece_set,
         })
         .marked::<SimpleMarker<SyncOnline>>()
         .build();
@@ -81,13 +95,92 @@ macro_rules! deserialize_individually {
             &mut $data.1, // marker
             &mut $data.2, // allocater
             &mut $de,
-        )
-        .unwrap();
+        )?;
         )*
     };
 }
 
-pub fn load_game(ecs: &mut World, data: &str) {
+/// A world with what a serialized game is loaded into, and nothing else.
+pub fn loadable_world() -> World {
+    let mut ecs = World::new();
+
+    ecs.register::<Position>();
+    ecs.register::<Polynomio>();
+    ecs.register::<Player>();
+    ecs.register::<SimpleMarker<SyncOnline>>();
+    ecs.register::<SerializeHelper>();
+
+    let empty_players: Vec<Entity> = Vec::new();
+    ecs.insert(SimpleMarkerAllocator::<SyncOnline>::new());
+    ecs.insert(Map::new(0, 0, 1, 1));
+    ecs.insert(empty_players);
+    ecs.insert(0 as usize);
+    ecs.insert(Mode::Initialize);
+    ecs.insert(Rules::default());
+    ecs.insert(None as Option<UndoRequest>);
+    ecs.insert(Vec::<GameEvent>::new());
+    ecs.insert(None as Option<Series>);
+    ecs.insert(Seating::one_seat_per_color(0));
+    ecs.insert(PieceSet {
+        geometry: Geometry::Square,
+        pieces: Vec::new(),
+    });
+
+    ecs
+}
+
+/// Replaces the game with a serialized one. A malformed game is first loaded
+/// into a scratch world, so it is rejected before the running game is touched.
+pub fn load_game(ecs: &mut World, data: &str) -> Result<(), serde_json::Error> {
+    load_into(&mut loadable_world(), data)?;
+    load_into(ecs, data)
+}
+
+/// Rejects a game whose players, active player or board would be indexed out
+/// of range. `players` are sorted by id.
+fn check_game(h: &SerializeHelper, players: &[(Entity, &Player)]) -> Result<(), serde_json::Error> {
+    if players
+        .iter()
+        .enumerate()
+        .any(|(i, (_, p))| p.id != i as i32)
+    {
+        return Err(Error::custom("The players are not numbered in order."));
+    }
+    if h.active_player_id >= players.len() {
+        return Err(Error::custom(format!(
+            "No player #{} to play",
+            h.active_player_id + 1
+        )));
+    }
+    if h.seating.turn_order.iter().any(|&id| id >= players.len()) {
+        return Err(Error::custom(
+            "The turn order names a player not in the game.",
+        ));
+    }
+
+    let map = &h.map;
+    if map.map.len() != map.width * map.height {
+        return Err(Error::custom(format!(
+            "The board is {}x{} but has {} cells.",
+            map.width,
+            map.height,
+            map.map.len()
+        )));
+    }
+    let is_player = |id: i32| id >= 0 && (id as usize) < players.len();
+    if map.map.iter().any(|&tile| tile >= 0 && !is_player(tile))
+        || map.colors.keys().any(|&id| !is_player(id))
+        || map
+            .starts
+            .iter()
+            .any(|(&id, &p)| !is_player(id) || !map.point_isin(p))
+    {
+        return Err(Error::custom("The board names a player not in the game."));
+    }
+    Ok(())
+}
+
+fn load_into(ecs: &mut World, data: &str) -> Result<(), serde_json::Error> {
     {
         // Delete everything
         let mut to_delete = Vec::new();
@@ -117,23 +210,32 @@ pub fn load_game(ecs: &mut World, data: &str) {
         let helper = ecs.read_storage::<SerializeHelper>();
         let players = ecs.read_storage::<Player>();
 
+        let mut player_entity_vec = (&entities, &players)
+            .join()
+            .collect::<Vec<(Entity, &Player)>>();
+        player_entity_vec.sort_by(|a, b| (a.1.id).cmp(&b.1.id));
+
         for (e, h) in (&entities, &helper).join() {
+            check_game(h, &player_entity_vec)?;
             let mut worldmap = ecs.write_resource::<super::map::Map>();
             *worldmap = h.map.clone();
+            worldmap.track();
             let mut active_player_id = ecs.write_resource::<usize>();
             *active_player_id = h.active_player_id;
             let mut mode = ecs.write_resource::<Mode>();
             *mode = h.mode;
+            *ecs.write_resource::<Rules>() = h.rules.clone();
+            *ecs.write_resource::<Option<UndoRequest>>() = h.undo_request.clone();
+            *ecs.write_resource::<Vec<GameEvent>>() = h.history.clone();
+            *ecs.write_resource::<Option<Series>>() = h.series.clone();
+            *ecs.write_resource::<Seating>() = h.seating.clone();
+            *ecs.write_resource::<PieceSet>() = h.piece_set.clone();
             // let mut players = ecs.write_resource::<Vec<Entity>>();
             // *players = h.players.0.clone();
             deleteme = Some(e);
         }
 
         let mut player_entities = ecs.fetch_mut::<Vec<Entity>>();
-        let mut player_entity_vec = (&entities, &players)
-            .join()
-            .collect::<Vec<(Entity, &Player)>>();
-        player_entity_vec.sort_by(|a, b| (a.1.id).cmp(&b.1.id));
         *player_entities = player_entity_vec
             .iter()
             .map(|(e, _)| *e)
@@ -152,6 +254,20 @@ pub fn load_game(ecs: &mut World, data: &str) {
         //     *player_resource = e;
         // }
     }
-    ecs.delete_entity(deleteme.unwrap())
-        .expect("Unable to delete helper");
+    let helper = deleteme.ok_or_else(|| Error::custom("The game has no state."))?;
+    ecs.delete_entity(helper).expect("Unable to delete helper");
+
+    // Only the shape ids were sent.
+    let piece_set = ecs.fetch::<PieceSet>();
+    for polynomio in (&mut ecs.write_storage::<Polynomio>()).join() {
+        let shape = &polynomio.shape;
+        if shape.id >= piece_set.pieces.len()
+            || polynomio.orientation >= Shape::rotations(piece_set.geometry) * 2
+        {
+            return Err(Error::custom(format!("Unknown piece: {}", polynomio.name)));
+        }
+        polynomio.shape = piece_set.shape(shape.id);
+        polynomio.orient(polynomio.orientation);
+    }
+    Ok(())
 }
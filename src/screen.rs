@@ -0,0 +1,98 @@
+use super::{key_inputs, mouse_inputs, Input};
+use rltk::{FontCharType, GameState, Rltk, RGB};
+use specs::World;
+use std::convert::TryInto;
+
+/// What the game is drawn on: the rltk window, or a terminal with `--frontend tty`.
+pub trait Screen {
+    /// Size in cells, and the part of it that is shown at the zoom.
+    fn size(&mut self, zoom: f32) -> ((i32, i32), (i32, i32));
+
+    fn cls(&mut self);
+
+    fn print_color<S, X, Y>(&mut self, x: X, y: Y, fg: RGB, bg: RGB, text: S)
+    where
+        S: ToString,
+        X: TryInto<i32>,
+        Y: TryInto<i32>;
+
+    fn set<X, Y>(&mut self, x: X, y: Y, fg: RGB, bg: RGB, glyph: FontCharType)
+    where
+        X: TryInto<i32>,
+        Y: TryInto<i32>;
+
+    fn print<S, X, Y>(&mut self, x: X, y: Y, text: S)
+    where
+        S: ToString,
+        X: TryInto<i32>,
+        Y: TryInto<i32>,
+    {
+        let (fg, bg) = (RGB::named(rltk::WHITE), RGB::named(rltk::BLACK));
+        self.print_color(x, y, fg, bg, text);
+    }
+}
+
+impl Screen for Rltk {
+    fn size(&mut self, zoom: f32) -> ((i32, i32), (i32, i32)) {
+        if self.get_scale().0 != zoom {
+            self.set_scale(zoom, 0, 0);
+        }
+        let (width, height) = self.get_char_size();
+        let visible = ((width as f32 / zoom) as i32, (height as f32 / zoom) as i32);
+        ((width as i32, height as i32), visible)
+    }
+
+    fn cls(&mut self) {
+        Rltk::cls(self);
+    }
+
+    fn print_color<S, X, Y>(&mut self, x: X, y: Y, fg: RGB, bg: RGB, text: S)
+    where
+        S: ToString,
+        X: TryInto<i32>,
+        Y: TryInto<i32>,
+    {
+        Rltk::print_color(self, x, y, fg, bg, text);
+    }
+
+    fn set<X, Y>(&mut self, x: X, y: Y, fg: RGB, bg: RGB, glyph: FontCharType)
+    where
+        X: TryInto<i32>,
+        Y: TryInto<i32>,
+    {
+        Rltk::set(self, x, y, fg, bg, glyph);
+    }
+
+    fn print<S, X, Y>(&mut self, x: X, y: Y, text: S)
+    where
+        S: ToString,
+        X: TryInto<i32>,
+        Y: TryInto<i32>,
+    {
+        Rltk::print(self, x, y, text);
+    }
+}
+
+/// A game advanced one frame at a time by the inputs of that frame, whatever
+/// frontend reads them and draws it.
+pub trait Game {
+    fn ecs(&self) -> &World;
+
+    /// Whether the player to move is played from here.
+    fn local_turn(&self) -> bool;
+
+    fn frame<S: Screen>(&mut self, screen: &mut S, inputs: Vec<Input>, frame_time_ms: f32);
+}
+
+/// Runs a game in the rltk window, with the keys and the mouse.
+pub struct Window<G: Game>(pub G);
+
+impl<G: Game + 'static> GameState for Window<G> {
+    fn tick(&mut self, ctx: &mut Rltk) {
+        let mut inputs = key_inputs(self.0.ecs(), ctx.key, ctx.shift);
+        let local_turn = self.0.local_turn();
+        inputs.extend(mouse_inputs(self.0.ecs(), ctx, !inputs.is_empty(), local_turn));
+        let frame_time_ms = ctx.frame_time_ms;
+        self.0.frame(ctx, inputs, frame_time_ms);
+    }
+}
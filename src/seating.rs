@@ -0,0 +1,87 @@
+use super::GameEvent;
+use serde::{Deserialize, Serialize};
+
+/// Who plays a colour.
+#[derive(PartialEq, Copy, Clone, Serialize, Deserialize, Debug)]
+pub enum Owner {
+    Seat(usize),
+    /// Played in turn by every seat and not scored, like the neutral colour of a 3-player game.
+    Shared,
+}
+
+/// Maps the colours (`Player` entities, indexed by player id) to the seats
+/// (player slots) that control them.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct Seating {
+    pub seats: usize,
+    pub owners: Vec<Owner>,
+    /// Order in which the colours play.
+    pub turn_order: Vec<usize>,
+}
+
+impl Seating {
+    pub fn new(seats: usize, owners: Vec<Owner>) -> Self {
+        let turn_order = (0..owners.len()).collect();
+        Seating {
+            seats,
+            owners,
+            turn_order,
+        }
+    }
+
+    pub fn with_turn_order(mut self, turn_order: Vec<usize>) -> Self {
+        self.turn_order = turn_order;
+        self
+    }
+
+    /// Colours in the order they play after `color`, ending with `color` itself.
+    pub fn turns_after(&self, color: usize) -> Vec<usize> {
+        let n = self.turn_order.len();
+        let at = match self.turn_order.iter().position(|&c| c == color) {
+            Some(at) => at,
+            None => return self.turn_order.clone(),
+        };
+        (1..=n).map(|i| self.turn_order[(at + i) % n]).collect()
+    }
+
+    pub fn one_seat_per_color(n: usize) -> Self {
+        Seating::new(n, (0..n).map(Owner::Seat).collect())
+    }
+
+    pub fn owner(&self, color: usize) -> Owner {
+        self.owners.get(color).cloned().unwrap_or(Owner::Seat(color))
+    }
+
+    pub fn is_scored(&self, color: usize) -> bool {
+        self.owner(color) != Owner::Shared
+    }
+
+    pub fn colors_of(&self, seat: usize) -> Vec<usize> {
+        (0..self.owners.len())
+            .filter(|&color| self.owner(color) == Owner::Seat(seat))
+            .collect()
+    }
+
+    /// Seat playing the next turn of `color`. A shared colour moves on to the
+    /// next seat every time it has had a turn.
+    pub fn controller(&self, color: usize, history: &[GameEvent]) -> usize {
+        match self.owner(color) {
+            Owner::Seat(seat) => seat,
+            Owner::Shared => {
+                let turns = history
+                    .iter()
+                    .filter(|event| **event == GameEvent::TurnChange { from: color })
+                    .count();
+                turns % self.seats.max(1)
+            }
+        }
+    }
+
+    /// Seat that played the last turn.
+    pub fn last_mover(&self, history: &[GameEvent]) -> Option<usize> {
+        let last_turn = history
+            .iter()
+            .rposition(|event| matches!(event, GameEvent::TurnChange { .. }))?;
+        Some(self.controller(history[last_turn].player_id() as usize, &history[..last_turn]))
+    }
+}
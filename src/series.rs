@@ -0,0 +1,71 @@
+use serde::{Deserialize, Serialize};
+
+/// A match of several games. Scores are accumulated per player id.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct Series {
+    pub games: usize,
+    pub results: Vec<Vec<i32>>,
+}
+
+impl Series {
+    pub fn new(games: usize) -> Self {
+        Series {
+            games,
+            results: Vec::new(),
+        }
+    }
+
+    pub fn played(&self) -> usize {
+        self.results.len()
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.played() >= self.games
+    }
+
+    pub fn record(&mut self, scores: Vec<i32>) {
+        self.results.push(scores);
+    }
+
+    pub fn totals(&self) -> Vec<i32> {
+        let n = self.results.iter().map(|r| r.len()).max().unwrap_or(0);
+        (0..n)
+            .map(|i| self.results.iter().map(|r| r.get(i).cloned().unwrap_or(0)).sum())
+            .collect()
+    }
+
+    /// Rank of each player id by total score. Tied players share a rank.
+    pub fn ranks(&self) -> Vec<i32> {
+        let totals = self.totals();
+        totals
+            .iter()
+            .map(|total| totals.iter().filter(|t| *t > total).count() as i32 + 1)
+            .collect()
+    }
+
+    /// Player who starts the next game. The start moves on through the turn
+    /// order every game.
+    pub fn starting_player(&self, turn_order: &[usize]) -> usize {
+        turn_order[self.played() % turn_order.len()]
+    }
+}
+
+/// What the host writes after every update so a crashed host can resume the series.
+#[derive(Serialize, Deserialize)]
+pub struct SeriesFile {
+    pub series: Series,
+    pub game: Option<String>,
+}
+
+impl SeriesFile {
+    pub fn load(path: &str) -> std::io::Result<Self> {
+        let serialized = std::fs::read_to_string(path)?;
+        serde_json::from_str(&serialized)
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))
+    }
+
+    pub fn save(&self, path: &str) -> std::io::Result<()> {
+        let serialized = serde_json::to_string(self).expect("Failed to serialize the series.");
+        std::fs::write(path, serialized)
+    }
+}
@@ -168,7 +168,7 @@ impl WebSocketSession {
                     }
                     Ok(()) => {
                         self.slot = Some(slot);
-                        println!("Player ({}) sat the chair #{}", name, player_id);
+                        log!("Player ({}) sat the chair #{}", name, player_id);
                         self.accept(ctx, player_id);
                     }
                 }
@@ -242,7 +242,7 @@ impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for WebSocketSession
                     .expect("Failed to deserialize the client message.");
                 self.handle_client_message(ctx, &message);
             }
-            ws::Message::Binary(_) => println!("Unexpected binary"),
+            ws::Message::Binary(_) => log!("Unexpected binary"),
             ws::Message::Close(reason) => {
                 ctx.close(reason);
                 ctx.stop();
@@ -324,7 +324,7 @@ async fn echo_route(
     ws_monitor: Data<Addr<WebsocketSessionMonitor>>,
     slot_manager: Data<Mutex<PlayerSlotManager>>,
 ) -> Result<HttpResponse, Error> {
-    println!("Connected from {:?}", req.peer_addr().unwrap());
+    log!("Connected from {:?}", req.peer_addr().unwrap());
     let session = WebSocketSession {
         ism: ism_data,
         slot_manager,
@@ -0,0 +1,122 @@
+use super::Geometry;
+use rltk::Point;
+use serde::{Deserialize, Serialize};
+
+/// A piece of a piece set with all its orientations computed once.
+///
+/// Orientation `o` is the piece flipped when `o >= rotations` and then rotated
+/// right `o % rotations` times, so it can be sent and saved as a single number.
+/// Only the id is saved: the tables are built again from the piece set on load.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Shape {
+    /// Index of the piece in its piece set. Pieces of every player share it.
+    pub id: usize,
+    pub geometry: Geometry,
+    /// Cells of every orientation, around the same pivot as the piece set.
+    #[serde(skip)]
+    orientations: Vec<Vec<Point>>,
+    /// First orientation covering the same cells up to a move, for each orientation.
+    #[serde(skip)]
+    canonical: Vec<usize>,
+}
+
+impl Shape {
+    pub fn new(id: usize, cells: &[Point], geometry: Geometry) -> Self {
+        let rotations = Shape::rotations(geometry);
+        let orientations: Vec<Vec<Point>> = (0..rotations * 2)
+            .map(|orientation| {
+                let mut coods: Vec<Point> = cells
+                    .iter()
+                    .map(|&p| if orientation >= rotations { geometry.flip(p) } else { p })
+                    .collect();
+                for _ in 0..orientation % rotations {
+                    coods = coods.iter().map(|&p| geometry.rotate(p, true)).collect();
+                }
+                coods.sort_by_key(|p| (p.y, p.x));
+                coods
+            })
+            .collect();
+        let keys: Vec<Vec<(i32, i32)>> = orientations
+            .iter()
+            .map(|coods| normalized(coods, geometry))
+            .collect();
+        let canonical = keys
+            .iter()
+            .map(|key| keys.iter().position(|k| k == key).unwrap())
+            .collect();
+        Shape {
+            id,
+            geometry,
+            orientations,
+            canonical,
+        }
+    }
+
+    /// Number of rotation steps in a full turn: 4 for squares, 6 for triangles.
+    pub fn rotations(geometry: Geometry) -> usize {
+        match geometry {
+            Geometry::Square => 4,
+            Geometry::Triangle => 6,
+        }
+    }
+
+    /// Number of orientation indices: 8 for squares, 12 for triangles.
+    pub fn orientation_count(&self) -> usize {
+        self.orientations.len()
+    }
+
+    pub fn cells(&self, orientation: usize) -> &[Point] {
+        &self.orientations[orientation]
+    }
+
+    pub fn size(&self) -> usize {
+        self.orientations[0].len()
+    }
+
+    pub fn rotate(&self, orientation: usize, right: bool) -> usize {
+        let rotations = Shape::rotations(self.geometry);
+        let turn = if right { 1 } else { rotations - 1 };
+        orientation - orientation % rotations + (orientation + turn) % rotations
+    }
+
+    pub fn flip(&self, orientation: usize) -> usize {
+        // A flip turns the following rotations the other way.
+        let rotations = Shape::rotations(self.geometry);
+        let flipped = if orientation >= rotations { 0 } else { rotations };
+        flipped + (rotations - orientation % rotations) % rotations
+    }
+
+    /// The orientation that stands for every orientation covering the same cells.
+    pub fn canonical(&self, orientation: usize) -> usize {
+        self.canonical[orientation]
+    }
+
+    /// Orientations that differ from each other by more than a move.
+    pub fn distinct(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.orientations.len()).filter(move |&o| self.canonical[o] == o)
+    }
+
+    /// Whether both shapes cover the same cells in some orientation.
+    pub fn same_as(&self, other: &Shape) -> bool {
+        let key = normalized(other.cells(0), other.geometry);
+        self.geometry == other.geometry
+            && self
+                .distinct()
+                .any(|o| normalized(self.cells(o), self.geometry) == key)
+    }
+}
+
+/// Sorted cells moved so that the upper left lies near the origin, so that two
+/// orientations covering the same shape compare equal.
+fn normalized(coods: &[Point], geometry: Geometry) -> Vec<(i32, i32)> {
+    let min_x = coods.iter().map(|p| p.x).min().unwrap_or(0);
+    let min_y = coods.iter().map(|p| p.y).min().unwrap_or(0);
+    // Triangles can only be moved by an even offset without turning them over.
+    let min_x = match geometry {
+        Geometry::Triangle if (min_x + min_y).rem_euclid(2) != 0 => min_x - 1,
+        _ => min_x,
+    };
+    let mut key: Vec<(i32, i32)> = coods.iter().map(|p| (p.x - min_x, p.y - min_y)).collect();
+    key.sort_unstable();
+    key
+}
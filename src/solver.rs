@@ -0,0 +1,161 @@
+use super::{legal_moves, legal_moves_at, BitBoard, Map, Move, Shape};
+use std::time::{Duration, Instant};
+
+/// The best line found by the solver.
+#[derive(Clone, Debug)]
+pub struct Solution {
+    /// Cells covered by the pieces of `moves`.
+    pub placed: usize,
+    pub moves: Vec<Move>,
+    /// Cells of all the pieces that were left to place.
+    pub remaining: usize,
+    /// False when the search was cut by its budget, so a better line may exist.
+    pub exhaustive: bool,
+}
+
+impl Solution {
+    pub fn places_all(&self) -> bool {
+        self.placed == self.remaining
+    }
+}
+
+/// Limits of a search. The search stops at whichever comes first.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Budget {
+    pub nodes: Option<usize>,
+    pub time: Option<Duration>,
+}
+
+struct Piece {
+    id: usize,
+    shape: Shape,
+    /// An earlier piece of the same shape. Identical pieces are placed in order
+    /// so that the search does not try them in every permutation.
+    same_as: Option<usize>,
+}
+
+struct Solver {
+    board: BitBoard,
+    player_id: i32,
+    free_placement: bool,
+    pieces: Vec<Piece>,
+    used: Vec<bool>,
+    current: Vec<Move>,
+    placed: usize,
+    best: Solution,
+    nodes: usize,
+    budget: Budget,
+    started: Instant,
+    cut: bool,
+}
+
+/// Searches for the line placing as many cells as possible with the given pieces,
+/// by backtracking over the legal moves. Pieces are tried largest first.
+pub fn solve(
+    map: &Map,
+    player_id: i32,
+    pieces: &[(usize, Shape)],
+    budget: Budget,
+) -> Solution {
+    let mut sorted: Vec<&(usize, Shape)> = pieces.iter().collect();
+    sorted.sort_by_key(|(id, shape)| (std::cmp::Reverse(shape.size()), *id));
+
+    let mut solver_pieces: Vec<Piece> = Vec::new();
+    for (id, shape) in sorted {
+        let same_as = solver_pieces.iter().position(|p| p.shape.same_as(shape));
+        solver_pieces.push(Piece {
+            id: *id,
+            shape: shape.clone(),
+            same_as,
+        });
+    }
+
+    let remaining = solver_pieces.iter().map(|p| p.shape.size()).sum();
+    let mut solver = Solver {
+        board: BitBoard::from_map(map),
+        player_id,
+        free_placement: map.free_placement,
+        used: vec![false; solver_pieces.len()],
+        pieces: solver_pieces,
+        current: Vec::new(),
+        placed: 0,
+        best: Solution {
+            placed: 0,
+            moves: Vec::new(),
+            remaining,
+            exhaustive: true,
+        },
+        nodes: 0,
+        budget,
+        started: Instant::now(),
+        cut: false,
+    };
+    solver.search(remaining);
+    solver.best.exhaustive = !solver.cut;
+    solver.best
+}
+
+impl Solver {
+    fn out_of_budget(&self) -> bool {
+        self.budget.nodes.map_or(false, |nodes| self.nodes >= nodes)
+            || self
+                .budget
+                .time
+                .map_or(false, |time| self.started.elapsed() >= time)
+    }
+
+    fn search(&mut self, remaining: usize) {
+        if self.out_of_budget() {
+            self.cut = true;
+            return;
+        }
+        self.nodes += 1;
+
+        if self.placed > self.best.placed {
+            self.best.placed = self.placed;
+            self.best.moves = self.current.clone();
+        }
+        let empty = self.board.empty_cells();
+        if self.best.placed == self.best.remaining
+            || self.placed + remaining.min(empty) <= self.best.placed
+        {
+            return;
+        }
+
+        let candidates: Vec<(usize, &Shape)> = (0..self.pieces.len())
+            .filter(|&i| !self.used[i])
+            .filter(|&i| self.pieces[i].same_as.map_or(true, |j| self.used[j]))
+            .map(|i| (i, &self.pieces[i].shape))
+            .collect();
+        // When the pieces fill the board exactly, the first empty cell must be
+        // covered by the next piece, which cuts every other branch.
+        let moves = if self.free_placement && remaining == empty {
+            let first = self.board.first_empty().unwrap();
+            legal_moves_at(&self.board, self.player_id, &candidates, &[first])
+        } else {
+            legal_moves(&self.board, self.player_id, &candidates)
+        };
+
+        for m in moves {
+            let size = self.pieces[m.piece].shape.size();
+            self.board.put(m.position, &m.coods, self.player_id);
+            self.used[m.piece] = true;
+            self.placed += size;
+            self.current.push(Move {
+                piece: self.pieces[m.piece].id,
+                ..m.clone()
+            });
+
+            self.search(remaining - size);
+
+            self.current.pop();
+            self.placed -= size;
+            self.used[m.piece] = false;
+            self.board.remove(m.position, &m.coods, self.player_id);
+
+            if self.cut || self.best.placed == self.best.remaining {
+                return;
+            }
+        }
+    }
+}
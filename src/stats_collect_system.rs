@@ -1,28 +1,29 @@
-use super::{Player, Polynomio};
+use super::{GameEvent, Owner, Player, Polynomio, Rules, Seating};
 use specs::Entity;
 use specs::ReadExpect;
 use specs::ReadStorage;
 use specs::System;
 use specs::WriteStorage;
 
-pub struct StatsCollectSystem {
-    pub winner: usize,
-}
+pub struct StatsCollectSystem {}
 
 impl<'a> System<'a> for StatsCollectSystem {
     type SystemData = (
         ReadExpect<'a, Vec<Entity>>,
+        ReadExpect<'a, Rules>,
+        ReadExpect<'a, Vec<GameEvent>>,
+        ReadExpect<'a, Seating>,
         WriteStorage<'a, Player>,
         ReadStorage<'a, Polynomio>,
     );
     fn run(&mut self, data: Self::SystemData) {
-        let (player_entities, mut players_store, polynomios_store) = data;
+        let (player_entities, rules, history, seating, mut players_store, polynomios_store) = data;
         let players: Vec<&Player> = player_entities
             .iter()
             .map(|pe| players_store.get(*pe).unwrap())
             .collect();
 
-        let mut totals: Vec<(i32, usize)> = Vec::new();
+        let mut totals: Vec<(i32, i32, usize)> = Vec::new();
         for (i, player) in players.iter().enumerate() {
             let polynomios: Vec<&Polynomio> = player
                 .polynomios
@@ -35,15 +36,114 @@ impl<'a> System<'a> for StatsCollectSystem {
                     total += p.coods.len() as i32;
                 }
             }
-            totals.push((total, i));
+
+            let last_put = history.iter().rev().find_map(|event| match event {
+                GameEvent::Put {
+                    player_id,
+                    polynomio_id,
+                    ..
+                } if *player_id == player.id => Some(*polynomio_id),
+                _ => None,
+            });
+            let monomino_last = matches!(last_put, Some(id) if polynomios[id].coods.len() == 1);
+
+            totals.push((rules.scoring.score(total, monomino_last), total, i));
         }
 
-        totals.sort_by_key(|e| (e.0, -(e.1 as i32)));
-        self.winner = totals[0].1;
-        for (rank, (total, i)) in totals.iter().enumerate() {
+        let scores: Vec<i32> = totals.iter().map(|(score, _, _)| *score).collect();
+        for ((score, rank, tied), (_, total, i)) in
+            standings(&scores, &seating).into_iter().zip(totals.iter())
+        {
             let p = player_entities.get(*i).unwrap();
-            players_store.get_mut(*p).unwrap().remaining_tiles = *total;
-            players_store.get_mut(*p).unwrap().rank = (rank + 1) as i32;
+            let player = players_store.get_mut(*p).unwrap();
+            player.remaining_tiles = *total;
+            player.score = score;
+            player.rank = rank;
+            player.tied = tied;
         }
     }
 }
+
+/// Score, rank and whether the rank is shared, of every colour given the
+/// colours' own scores. A seat scores the sum of its colours. Shared colours
+/// keep their own score and are not ranked (rank 0).
+fn standings(scores: &[i32], seating: &Seating) -> Vec<(i32, i32, bool)> {
+    let seats: Vec<i32> = (0..seating.seats)
+        .map(|seat| {
+            seating
+                .colors_of(seat)
+                .iter()
+                .filter_map(|&i| scores.get(i))
+                .sum()
+        })
+        .collect();
+
+    // Tied seats share a rank, and the next rank is skipped (1, 1, 3, ...).
+    scores
+        .iter()
+        .enumerate()
+        .map(|(i, &score)| match seating.owners.get(i) {
+            Some(&Owner::Seat(seat)) if seat < seats.len() => {
+                let seat_score = seats[seat];
+                let rank = seats.iter().filter(|&&s| s > seat_score).count() as i32 + 1;
+                let tied = seats.iter().filter(|&&s| s == seat_score).count() > 1;
+                (seat_score, rank, tied)
+            }
+            _ => (score, 0, false),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tie_for_first_shares_the_rank_and_skips_the_next() {
+        let seating = Seating::one_seat_per_color(4);
+        assert_eq!(
+            standings(&[-3, -3, -5, -8], &seating),
+            vec![(-3, 1, true), (-3, 1, true), (-5, 3, false), (-8, 4, false)]
+        );
+    }
+
+    #[test]
+    fn tie_further_down_leaves_the_winner_alone() {
+        let seating = Seating::one_seat_per_color(4);
+        assert_eq!(
+            standings(&[15, -6, -2, -6], &seating),
+            vec![(15, 1, false), (-6, 3, true), (-2, 2, false), (-6, 3, true)]
+        );
+    }
+
+    #[test]
+    fn shared_colour_is_not_ranked() {
+        let owners = vec![
+            Owner::Seat(0),
+            Owner::Seat(1),
+            Owner::Seat(2),
+            Owner::Shared,
+        ];
+        let seating = Seating::new(3, owners);
+        // The shared colour has the best score but takes no rank from the seats.
+        assert_eq!(
+            standings(&[-4, -7, -4, 15], &seating),
+            vec![(-4, 1, true), (-7, 3, false), (-4, 1, true), (15, 0, false)]
+        );
+    }
+
+    #[test]
+    fn seat_scores_the_sum_of_its_colours() {
+        let owners = vec![
+            Owner::Seat(0),
+            Owner::Seat(1),
+            Owner::Seat(0),
+            Owner::Seat(1),
+        ];
+        let seating = Seating::new(2, owners);
+        assert_eq!(
+            standings(&[-2, -5, -3, 0], &seating),
+            vec![(-5, 1, true), (-5, 1, true), (-5, 1, true), (-5, 1, true)]
+        );
+    }
+}
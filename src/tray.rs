@@ -0,0 +1,91 @@
+use super::{analyze, Input, KeyBindings, Mode, Overlays, Player, Polynomio};
+use rltk::VirtualKeyCode;
+use specs::prelude::*;
+
+/// Inputs of the key pressed this frame. Shift + a letter or digit selects the
+/// next piece whose name starts with it, so `Shift+L` goes through L5 and L4, and
+/// `Shift+6` through the hexiamonds of Trigon. While the trays only show the
+/// playable pieces, Left / Right skip the others.
+pub fn key_inputs(ecs: &World, key: Option<VirtualKeyCode>, shift: bool) -> Vec<Input> {
+    let mode = *ecs.fetch::<Mode>();
+    if shift && (mode == Mode::Select || mode == Mode::Put) {
+        if let Some(c) = key.and_then(key_char) {
+            let target = next_piece(ecs, false, |name| name.to_ascii_uppercase().starts_with(c));
+            return match target {
+                Some(i) if mode == Mode::Put => {
+                    vec![Input::Cancel, Input::SelectPiece(i), Input::Enter]
+                }
+                Some(i) => vec![Input::SelectPiece(i)],
+                None => Vec::new(),
+            };
+        }
+    }
+
+    let input = match ecs.fetch::<KeyBindings>().input(key) {
+        Some(input) => input,
+        None => return Vec::new(),
+    };
+    if mode == Mode::Select && ecs.fetch::<Overlays>().playable {
+        let reverse = match input {
+            Input::Left => Some(true),
+            Input::Right => Some(false),
+            _ => None,
+        };
+        if let Some(i) = reverse.and_then(|reverse| next_piece(ecs, reverse, |_| true)) {
+            return vec![Input::SelectPiece(i)];
+        }
+    }
+    vec![input]
+}
+
+fn key_char(key: VirtualKeyCode) -> Option<char> {
+    let name = format!("{:?}", key);
+    let name = name.strip_prefix("Key").unwrap_or(&name);
+    match name.as_bytes() {
+        [c] if c.is_ascii_alphanumeric() => Some(*c as char),
+        _ => None,
+    }
+}
+
+/// The first piece after the selected one in tray order, or before it when
+/// `reverse`, that is still in the tray, matches the name and is playable while
+/// only playable pieces are shown.
+fn next_piece(ecs: &World, reverse: bool, matches: impl Fn(&str) -> bool) -> Option<usize> {
+    let (n, select, candidates) = candidates(ecs, matches);
+    (1..=n)
+        .map(|delta| {
+            if reverse {
+                (select + n - delta) % n
+            } else {
+                (select + delta) % n
+            }
+        })
+        .find(|i| candidates.contains(i))
+}
+
+/// Number of pieces of the player to move, the selected one, and the pieces a
+/// selection may go to.
+fn candidates(ecs: &World, matches: impl Fn(&str) -> bool) -> (usize, usize, Vec<usize>) {
+    let active_player_id = *ecs.fetch::<usize>();
+    let playable = if ecs.fetch::<Overlays>().playable {
+        analyze(ecs)
+            .into_iter()
+            .find(|analysis| analysis.player_id == active_player_id as i32)
+            .map(|analysis| analysis.pieces)
+    } else {
+        None
+    };
+
+    let players = ecs.read_storage::<Player>();
+    let polynomios = ecs.read_storage::<Polynomio>();
+    let player = match players.get(ecs.fetch::<Vec<Entity>>()[active_player_id]) {
+        Some(player) => player,
+        None => return (0, 0, Vec::new()),
+    };
+    let candidates = (0..player.polynomios.len())
+        .filter(|&i| !player.fixed[i])
+        .filter(|i| playable.as_ref().map_or(true, |pieces| pieces.contains(i)))
+        .filter(|&i| matches(&polynomios.get(player.polynomios[i]).unwrap().name))
+        .collect();
+    (player.polynomios.len(), player.select, candidates)
+}
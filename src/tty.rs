@@ -0,0 +1,267 @@
+use super::{key_code, key_inputs, log_to_file, Game, Screen, LOG_FILE};
+use crossterm::cursor::{Hide, MoveTo, Show};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyModifiers};
+use crossterm::style::{Color, Print, ResetColor, SetBackgroundColor, SetForegroundColor};
+use crossterm::terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{execute, queue};
+use rltk::{FontCharType, VirtualKeyCode, RGB};
+use std::convert::TryInto;
+use std::io::Write;
+use std::time::{Duration, Instant};
+
+/// How long a frame waits for a key.
+const FRAME: Duration = Duration::from_millis(33);
+
+#[derive(Clone, Copy, PartialEq)]
+struct Cell {
+    glyph: char,
+    fg: RGB,
+    bg: RGB,
+}
+
+const BLANK: Cell = Cell {
+    glyph: ' ',
+    fg: RGB {
+        r: 1.,
+        g: 1.,
+        b: 1.,
+    },
+    bg: RGB {
+        r: 0.,
+        g: 0.,
+        b: 0.,
+    },
+};
+
+/// A terminal drawn with ANSI colours, one cell of the console per character.
+/// Only the cells that changed since the last frame are written.
+pub struct Terminal {
+    out: Box<dyn Write>,
+    size: (i32, i32),
+    cells: Vec<Cell>,
+    /// What the terminal shows, `None` where it is unknown.
+    drawn: Vec<Option<Cell>>,
+}
+
+/// The controlling terminal, or stdout when there is none.
+fn tty_out() -> Box<dyn Write> {
+    match std::fs::OpenOptions::new().write(true).open("/dev/tty") {
+        Ok(tty) => Box::new(tty),
+        Err(_) => Box::new(std::io::stdout()),
+    }
+}
+
+/// Leaves the alternate screen and raw mode.
+fn restore() {
+    let _ = execute!(tty_out(), ResetColor, Show, LeaveAlternateScreen);
+    let _ = terminal::disable_raw_mode();
+}
+
+impl Terminal {
+    /// Takes over the terminal until the `Terminal` is dropped. A panic gives
+    /// the terminal back before its message is printed.
+    fn open() -> crossterm::Result<Self> {
+        let mut out = tty_out();
+        terminal::enable_raw_mode()?;
+        execute!(out, EnterAlternateScreen, Hide)?;
+        let default_hook = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            restore();
+            default_hook(info);
+        }));
+
+        let mut terminal = Terminal {
+            out,
+            size: (0, 0),
+            cells: Vec::new(),
+            drawn: Vec::new(),
+        };
+        terminal.size(1.);
+        Ok(terminal)
+    }
+
+    fn put<X: TryInto<i32>, Y: TryInto<i32>>(&mut self, x: X, y: Y, cell: Cell) {
+        let (x, y) = match (x.try_into(), y.try_into()) {
+            (Ok(x), Ok(y)) => (x, y),
+            _ => return,
+        };
+        if x >= 0 && y >= 0 && x < self.size.0 && y < self.size.1 {
+            self.cells[(y * self.size.0 + x) as usize] = cell;
+        }
+    }
+
+    fn flush(&mut self) -> crossterm::Result<()> {
+        let mut cursor = None;
+        let mut colors = None;
+        for (i, cell) in self.cells.iter().enumerate() {
+            if self.drawn[i] == Some(*cell) {
+                continue;
+            }
+            let x = (i as i32 % self.size.0) as u16;
+            let y = (i as i32 / self.size.0) as u16;
+            if cursor != Some((x, y)) {
+                queue!(self.out, MoveTo(x, y))?;
+            }
+            if colors != Some((cell.fg, cell.bg)) {
+                queue!(
+                    self.out,
+                    SetForegroundColor(color(cell.fg)),
+                    SetBackgroundColor(color(cell.bg))
+                )?;
+                colors = Some((cell.fg, cell.bg));
+            }
+            queue!(self.out, Print(cell.glyph))?;
+            cursor = Some((x + 1, y));
+            self.drawn[i] = Some(*cell);
+        }
+        self.out.flush()?;
+        Ok(())
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        restore();
+    }
+}
+
+fn color(rgb: RGB) -> Color {
+    let channel = |c: f32| (c.clamp(0., 1.) * 255.) as u8;
+    Color::Rgb {
+        r: channel(rgb.r),
+        g: channel(rgb.g),
+        b: channel(rgb.b),
+    }
+}
+
+impl Screen for Terminal {
+    /// A terminal cannot zoom: its font size is up to the terminal.
+    fn size(&mut self, _zoom: f32) -> ((i32, i32), (i32, i32)) {
+        if let Ok((width, height)) = terminal::size() {
+            let size = (width as i32, height as i32);
+            if size != self.size {
+                self.size = size;
+                self.cells = vec![BLANK; (size.0 * size.1) as usize];
+                self.drawn = vec![None; self.cells.len()];
+                let _ = queue!(self.out, ResetColor, Clear(ClearType::All));
+            }
+        }
+        (self.size, self.size)
+    }
+
+    fn cls(&mut self) {
+        for cell in self.cells.iter_mut() {
+            *cell = BLANK;
+        }
+    }
+
+    fn print_color<S, X, Y>(&mut self, x: X, y: Y, fg: RGB, bg: RGB, text: S)
+    where
+        S: ToString,
+        X: TryInto<i32>,
+        Y: TryInto<i32>,
+    {
+        let (x, y) = match (x.try_into(), y.try_into()) {
+            (Ok(x), Ok(y)) => (x, y),
+            _ => return,
+        };
+        for (i, glyph) in text.to_string().chars().enumerate() {
+            self.put(x + i as i32, y, Cell { glyph, fg, bg });
+        }
+    }
+
+    fn set<X, Y>(&mut self, x: X, y: Y, fg: RGB, bg: RGB, glyph: FontCharType)
+    where
+        X: TryInto<i32>,
+        Y: TryInto<i32>,
+    {
+        let glyph = rltk::to_char(glyph as u8);
+        self.put(x, y, Cell { glyph, fg, bg });
+    }
+}
+
+/// Runs a game in the terminal until Ctrl+C or Ctrl+Q, reading the keys into
+/// the same inputs as the window. There is no mouse. The log goes to
+/// `LOG_FILE`.
+pub fn run_tty<G: Game>(mut game: G) -> crossterm::Result<()> {
+    log_to_file();
+    log!("Playing in the terminal. The log goes to {}", LOG_FILE);
+    let mut terminal = Terminal::open()?;
+    play(&mut game, &mut terminal)
+}
+
+fn play<G: Game>(game: &mut G, terminal: &mut Terminal) -> crossterm::Result<()> {
+    let mut last_frame = Instant::now();
+    loop {
+        let mut inputs = Vec::new();
+        if event::poll(FRAME)? {
+            if let Event::Key(key) = event::read()? {
+                let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+                if ctrl && (key.code == KeyCode::Char('c') || key.code == KeyCode::Char('q')) {
+                    return Ok(());
+                }
+                if let Some((key, shift)) = virtual_key(key) {
+                    inputs = key_inputs(game.ecs(), Some(key), shift);
+                }
+            }
+        }
+
+        let now = Instant::now();
+        let frame_time_ms = now.duration_since(last_frame).as_secs_f32() * 1000.;
+        last_frame = now;
+        game.frame(terminal, inputs, frame_time_ms);
+        terminal.flush()?;
+    }
+}
+
+/// The key of the window that gives the same input, and whether Shift is held.
+fn virtual_key(key: KeyEvent) -> Option<(VirtualKeyCode, bool)> {
+    let shift = key.modifiers.contains(KeyModifiers::SHIFT);
+    let code = match key.code {
+        KeyCode::Left => VirtualKeyCode::Left,
+        KeyCode::Right => VirtualKeyCode::Right,
+        KeyCode::Up => VirtualKeyCode::Up,
+        KeyCode::Down => VirtualKeyCode::Down,
+        KeyCode::Enter => VirtualKeyCode::Return,
+        KeyCode::Esc => VirtualKeyCode::Escape,
+        KeyCode::Backspace => VirtualKeyCode::Back,
+        KeyCode::Tab => VirtualKeyCode::Tab,
+        KeyCode::Delete => VirtualKeyCode::Delete,
+        KeyCode::Insert => VirtualKeyCode::Insert,
+        KeyCode::Home => VirtualKeyCode::Home,
+        KeyCode::End => VirtualKeyCode::End,
+        KeyCode::PageUp => VirtualKeyCode::PageUp,
+        KeyCode::PageDown => VirtualKeyCode::PageDown,
+        KeyCode::Char(c) => {
+            let (code, shifted) = char_key(c)?;
+            return Some((code, shift || shifted));
+        }
+        _ => return None,
+    };
+    Some((code, shift))
+}
+
+/// The key typing the character on a US keyboard, and whether it takes Shift.
+/// Terminals send characters, so `Shift+6` arrives as `^`.
+fn char_key(c: char) -> Option<(VirtualKeyCode, bool)> {
+    if let Some(digit) = ")!@#$%^&*(".find(c) {
+        return Some((key_code(&format!("Key{}", digit))?, true));
+    }
+    let (name, shifted) = match c {
+        'a'..='z' => (c.to_ascii_uppercase().to_string(), false),
+        'A'..='Z' => (c.to_string(), true),
+        '0'..='9' => (format!("Key{}", c), false),
+        ' ' => ("Space".to_string(), false),
+        ',' | '<' => ("Comma".to_string(), c == '<'),
+        '.' | '>' => ("Period".to_string(), c == '>'),
+        '/' | '?' => ("Slash".to_string(), c == '?'),
+        ';' | ':' => ("Semicolon".to_string(), c == ':'),
+        '\'' | '"' => ("Apostrophe".to_string(), c == '"'),
+        '-' | '_' => ("Minus".to_string(), c == '_'),
+        '=' | '+' => ("Equals".to_string(), c == '+'),
+        '[' | '{' => ("LBracket".to_string(), c == '{'),
+        ']' | '}' => ("RBracket".to_string(), c == '}'),
+        _ => return None,
+    };
+    Some((key_code(&name)?, shifted))
+}